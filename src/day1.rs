@@ -1,15 +1,230 @@
+use num_bigint::BigInt;
 use rocket::http::Status;
-use rocket::{get, routes};
+use rocket::response::status::BadRequest;
+use rocket::serde::json::{Json, Value};
+use rocket::serde::Serialize;
+use rocket::{get, post, routes, FromForm};
 use std::ffi::OsStr;
+use std::num::IntErrorKind;
 use std::path::PathBuf;
 
+mod expr;
+
 pub fn routes() -> Vec<rocket::Route> {
-    routes![calculate]
+    routes![batch, calculate, calculate_big, evaluate_expr]
+}
+
+#[derive(Debug, Default, FromForm)]
+struct CalcQuery<'r> {
+    op: Option<&'r str>,
+    transform: Option<&'r str>,
+    arg: Option<&'r str>,
+    radix: Option<&'r str>,
+    output_radix: Option<&'r str>,
+}
+
+// Folds the packets with the selected reducer (default xor) and applies the final transform (default cube)
+#[get("/<path..>?<query..>")]
+fn calculate(path: PathBuf, query: CalcQuery<'_>) -> Result<String, Status> {
+    let reducer = find_reducer(query.op.unwrap_or("xor")).ok_or(Status::BadRequest)?;
+    let transform = Transform::from_query(query.transform, query.arg)?;
+    let radix = parse_radix(query.radix)?;
+    let output_radix = parse_radix(query.output_radix)?.unwrap_or(10);
+
+    let packets: Vec<Option<&str>> = path.iter().map(|el| el.to_str()).collect();
+    let result = reduce_packets(&packets, radix, reducer, &transform).map_err(|e| {
+        println!("Failed to calculate {path:?}: {e:?}");
+        Status::BadRequest
+    })?;
+    Ok(format_radix(result, output_radix))
 }
 
-#[get("/<path..>")]
-fn calculate(path: PathBuf) -> Result<String, Status> {
-    let mut acc: isize = 0;
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(crate = "rocket::serde", tag = "kind", rename_all = "snake_case")]
+enum PacketError {
+    TooManyPackets { count: usize, max: usize },
+    ParseFailure { packet: usize },
+    Overflow,
+    NoPackets,
+}
+
+// Packets that aren't valid strings come through as None and fail to parse
+fn reduce_packets(
+    packets: &[Option<&str>],
+    radix: Option<u32>,
+    reducer: &Reducer,
+    transform: &Transform,
+) -> Result<isize, PacketError> {
+    if packets.len() > reducer.max_packets {
+        return Err(PacketError::TooManyPackets {
+            count: packets.len(),
+            max: reducer.max_packets,
+        });
+    }
+
+    let mut acc: Option<isize> = None;
+    for (index, packet) in packets.iter().enumerate() {
+        let i = packet
+            .ok_or("String conversion failed")
+            .and_then(|p| parse_os_str_with_radix(OsStr::new(p), radix))
+            .map_err(|e| match e {
+                PACKET_OUT_OF_RANGE => PacketError::Overflow,
+                _ => PacketError::ParseFailure { packet: index },
+            })?;
+
+        acc = match acc {
+            Some(acc) => Some((reducer.fold)(acc, i).ok_or(PacketError::Overflow)?),
+            None => Some(i),
+        }
+    }
+
+    // Reducers without an identity (min, max) need at least one packet
+    let acc = acc.or(reducer.identity).ok_or(PacketError::NoPackets)?;
+    transform.apply(acc).ok_or(PacketError::Overflow)
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(crate = "rocket::serde", untagged)]
+enum BatchResult {
+    Cube { cube: isize },
+    Error { error: PacketError },
+}
+
+// XOR-cubes every packet list independently so one bad item doesn't fail the whole batch
+#[post("/batch", data = "<items>")]
+fn batch(items: Json<Vec<Vec<Value>>>) -> Json<Vec<BatchResult>> {
+    let reducer = find_reducer("xor").unwrap();
+    let transform = Transform::Pow(3);
+
+    let results = items
+        .iter()
+        .map(|item| {
+            // Packets may be sent as JSON numbers or strings; anything else fails to parse
+            let packets: Vec<Option<String>> = item
+                .iter()
+                .map(|packet| match packet {
+                    // 1e30 is a whole number too, just one that overflows a packet
+                    Value::Number(n) => match n.as_f64() {
+                        Some(f) if n.is_f64() && f.is_finite() && f.fract() == 0.0 => {
+                            Some(format!("{f:.0}"))
+                        }
+                        _ => Some(n.to_string()),
+                    },
+                    Value::String(s) => Some(s.clone()),
+                    _ => None,
+                })
+                .collect();
+            let packets: Vec<Option<&str>> = packets.iter().map(|p| p.as_deref()).collect();
+
+            match reduce_packets(&packets, None, reducer, &transform) {
+                Ok(cube) => BatchResult::Cube { cube },
+                Err(error) => BatchResult::Error { error },
+            }
+        })
+        .collect();
+
+    Json(results)
+}
+
+struct Reducer {
+    name: &'static str,
+    identity: Option<isize>,
+    fold: fn(isize, isize) -> Option<isize>,
+    max_packets: usize,
+}
+
+// Add new reducers here; the route looks them up by name from the `op` query parameter
+const REDUCERS: &[Reducer] = &[
+    Reducer {
+        name: "xor",
+        identity: Some(0),
+        fold: |a, b| Some(a ^ b),
+        max_packets: 20,
+    },
+    Reducer {
+        name: "and",
+        identity: Some(-1),
+        fold: |a, b| Some(a & b),
+        max_packets: 20,
+    },
+    Reducer {
+        name: "or",
+        identity: Some(0),
+        fold: |a, b| Some(a | b),
+        max_packets: 20,
+    },
+    Reducer {
+        name: "sum",
+        identity: Some(0),
+        fold: isize::checked_add,
+        max_packets: 20,
+    },
+    Reducer {
+        name: "product",
+        identity: Some(1),
+        fold: isize::checked_mul,
+        max_packets: 20,
+    },
+    Reducer {
+        name: "min",
+        identity: None,
+        fold: |a, b| Some(a.min(b)),
+        max_packets: 20,
+    },
+    Reducer {
+        name: "max",
+        identity: None,
+        fold: |a, b| Some(a.max(b)),
+        max_packets: 20,
+    },
+];
+
+fn find_reducer(name: &str) -> Option<&'static Reducer> {
+    REDUCERS.iter().find(|r| r.name == name)
+}
+
+#[derive(Debug, PartialEq)]
+enum Transform {
+    Pow(u32),
+    Mod(isize),
+    Identity,
+}
+
+impl Transform {
+    // No transform given means the original cube
+    fn from_query(name: Option<&str>, arg: Option<&str>) -> Result<Transform, Status> {
+        let arg = match arg {
+            Some(arg) => Some(arg.parse::<isize>().map_err(|_| Status::BadRequest)?),
+            None => None,
+        };
+
+        match name.unwrap_or("pow") {
+            "pow" => {
+                let exp = u32::try_from(arg.unwrap_or(3)).map_err(|_| Status::BadRequest)?;
+                Ok(Transform::Pow(exp))
+            }
+            "mod" => match arg {
+                Some(0) | None => Err(Status::BadRequest),
+                Some(m) => Ok(Transform::Mod(m)),
+            },
+            "identity" => Ok(Transform::Identity),
+            _ => Err(Status::BadRequest),
+        }
+    }
+
+    fn apply(&self, value: isize) -> Option<isize> {
+        match self {
+            Transform::Pow(exp) => value.checked_pow(*exp),
+            Transform::Mod(m) => value.checked_rem_euclid(*m),
+            Transform::Identity => Some(value),
+        }
+    }
+}
+
+// Same XOR-cube as `calculate` but with arbitrary precision, so huge (and negative) packet IDs never overflow
+#[get("/big/<path..>")]
+fn calculate_big(path: PathBuf) -> Result<String, Status> {
+    let mut acc = BigInt::default();
     let mut count = 0;
 
     for el in path.iter() {
@@ -18,7 +233,7 @@ fn calculate(path: PathBuf) -> Result<String, Status> {
             return Err(Status::BadRequest);
         }
 
-        match parse_os_str_to_i32(el) {
+        match parse_os_str_to_bigint(el) {
             Ok(i) => acc ^= i,
             Err(_) => return Err(Status::BadRequest),
         }
@@ -27,23 +242,117 @@ fn calculate(path: PathBuf) -> Result<String, Status> {
     Ok(result.to_string())
 }
 
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct ExprErrorResponse {
+    error: String,
+    offset: usize,
+}
+
+#[post("/expr", data = "<raw>")]
+fn evaluate_expr(raw: &str) -> Result<String, BadRequest<Json<ExprErrorResponse>>> {
+    match expr::evaluate(raw) {
+        Ok(result) => Ok(result.to_string()),
+        Err(e) => {
+            println!("Failed to evaluate expression {raw}: {e}");
+            Err(BadRequest(Json(ExprErrorResponse {
+                error: e.to_string(),
+                offset: e.offset(),
+            })))
+        }
+    }
+}
+
 fn parse_os_str_to_i32(os_str: &OsStr) -> Result<isize, &'static str> {
+    parse_os_str_with_radix(os_str, None)
+}
+
+// A well-formed number too big or too small for a packet
+const PACKET_OUT_OF_RANGE: &str = "Packet out of range";
+
+// Detects 0x/0o/0b prefixes (after an optional sign). An explicit radix overrides them, and only a
+// prefix naming that radix is stripped: with radix 16, "0b1010" is the hex number 0xB1010.
+fn parse_os_str_with_radix(os_str: &OsStr, radix: Option<u32>) -> Result<isize, &'static str> {
+    let str_slice = os_str.to_str().ok_or("String conversion failed")?;
+
+    let (sign, unsigned) = match str_slice.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", str_slice.strip_prefix('+').unwrap_or(str_slice)),
+    };
+    let detected = match unsigned.get(..2) {
+        Some("0x") | Some("0X") => Some(16),
+        Some("0o") | Some("0O") => Some(8),
+        Some("0b") | Some("0B") => Some(2),
+        _ => None,
+    };
+    let (radix, digits) = match (radix, detected) {
+        (Some(requested), Some(prefix)) if requested == prefix => (requested, &unsigned[2..]),
+        (Some(requested), _) => (requested, unsigned),
+        (None, Some(prefix)) => (prefix, &unsigned[2..]),
+        (None, None) => (10, unsigned),
+    };
+
+    // from_str_radix would accept a second sign, so reject it here
+    if digits.starts_with(['+', '-']) {
+        return Err("Parse to isize failed");
+    }
+    isize::from_str_radix(&format!("{sign}{digits}"), radix).map_err(|e| match e.kind() {
+        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => PACKET_OUT_OF_RANGE,
+        _ => "Parse to isize failed",
+    })
+}
+
+fn parse_radix(radix: Option<&str>) -> Result<Option<u32>, Status> {
+    match radix {
+        Some(radix) => match radix.parse::<u32>() {
+            Ok(radix) if (2..=36).contains(&radix) => Ok(Some(radix)),
+            _ => Err(Status::BadRequest),
+        },
+        None => Ok(None),
+    }
+}
+
+fn format_radix(value: isize, radix: u32) -> String {
+    if radix == 10 {
+        return value.to_string();
+    }
+
+    let mut remaining = value.unsigned_abs();
+    let mut digits = Vec::new();
+    loop {
+        let digit = (remaining % radix as usize) as u32;
+        digits.push(std::char::from_digit(digit, radix).unwrap());
+        remaining /= radix as usize;
+        if remaining == 0 {
+            break;
+        }
+    }
+    if value < 0 {
+        digits.push('-');
+    }
+
+    digits.iter().rev().collect()
+}
+
+fn parse_os_str_to_bigint(os_str: &OsStr) -> Result<BigInt, &'static str> {
     let str_slice = os_str.to_str().ok_or("String conversion failed")?;
     str_slice
-        .parse::<isize>()
-        .map_err(|_| "Parse to isize failed")
+        .parse::<BigInt>()
+        .map_err(|_| "Parse to BigInt failed")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use rocket::http::Status;
+    use rocket::local::blocking::Client;
+    use rocket::serde::json::json;
     use std::path::PathBuf;
 
     #[test]
     fn test_calculate_success() {
         let path = PathBuf::from("10");
-        let result = calculate(path).unwrap();
+        let result = calculate(path, CalcQuery::default()).unwrap();
         // Calculate the expected result: (10)**3 = 1000
         assert_eq!(result, "1000".to_string());
     }
@@ -51,7 +360,7 @@ mod tests {
     #[test]
     fn test_calculate_success_2() {
         let path = PathBuf::from("4/5/8/10");
-        let result = calculate(path).unwrap();
+        let result = calculate(path, CalcQuery::default()).unwrap();
         // Calculate the expected result: 4^5^8^10
         assert_eq!(result, "27".to_string());
     }
@@ -59,7 +368,7 @@ mod tests {
     #[test]
     fn test_20_packets_ok() {
         let path = PathBuf::from("0/0/0/0/0/0/0/0/0/0/0/0/0/0/0/0/0/0/0/0");
-        let result = calculate(path).unwrap();
+        let result = calculate(path, CalcQuery::default()).unwrap();
         // Calculate the expected result: (10)**3 = 1000
         assert_eq!(result, "0".to_string());
     }
@@ -67,7 +376,7 @@ mod tests {
     #[test]
     fn test_21_packets_bad() {
         let path = PathBuf::from("0/0/0/0/0/0/0/0/0/0/0/0/0/0/0/0/0/0/0/0/0");
-        let result = calculate(path);
+        let result = calculate(path, CalcQuery::default());
         // Calculate the expected result: (10)**3 = 1000
         assert_eq!(result, Err(Status::BadRequest));
     }
@@ -75,14 +384,430 @@ mod tests {
     #[test]
     fn test_overflow_bad() {
         let path = PathBuf::from("18446744073709551616");
-        let result = calculate(path);
+        let result = calculate(path, CalcQuery::default());
+        assert_eq!(result, Err(Status::BadRequest));
+    }
+
+    #[test]
+    fn test_cube_overflow_bad() {
+        let path = PathBuf::from("3000000");
+        let result = calculate(path, CalcQuery::default());
         assert_eq!(result, Err(Status::BadRequest));
     }
 
     #[test]
     fn test_calculate_failure() {
         let path = PathBuf::from("1/2/abc");
-        let result = calculate(path);
+        let result = calculate(path, CalcQuery::default());
+        assert_eq!(result, Err(Status::BadRequest));
+    }
+
+    #[test]
+    fn test_big_overflow_ok() {
+        let path = PathBuf::from("18446744073709551616");
+        let result = calculate_big(path).unwrap();
+        // 2**64 cubed is 2**192
+        assert_eq!(
+            result,
+            "6277101735386680763835789423207666416102355444464034512896".to_string()
+        );
+    }
+
+    #[test]
+    fn test_big_negative() {
+        let path = PathBuf::from("-4/5");
+        let result = calculate_big(path).unwrap();
+        // -4 ^ 5 = -7 in two's complement, cubed is -343
+        assert_eq!(result, "-343".to_string());
+    }
+
+    #[test]
+    fn test_big_matches_legacy() {
+        let path = PathBuf::from("4/5/8/10");
+        assert_eq!(
+            calculate_big(path.clone()),
+            calculate(path, CalcQuery::default())
+        );
+    }
+
+    #[test]
+    fn test_big_21_packets_bad() {
+        let path = PathBuf::from("0/0/0/0/0/0/0/0/0/0/0/0/0/0/0/0/0/0/0/0/0");
+        let result = calculate_big(path);
+        assert_eq!(result, Err(Status::BadRequest));
+    }
+
+    #[test]
+    fn test_big_failure() {
+        let path = PathBuf::from("1/2/abc");
+        let result = calculate_big(path);
         assert_eq!(result, Err(Status::BadRequest));
     }
+
+    #[test]
+    fn test_sum_pow() {
+        let path = PathBuf::from("1/2/3");
+        let result = calculate(
+            path,
+            CalcQuery {
+                op: Some("sum"),
+                transform: Some("pow"),
+                arg: Some("2"),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(result, "36".to_string());
+    }
+
+    #[test]
+    fn test_product_mod() {
+        let path = PathBuf::from("4/5/-3");
+        let result = calculate(
+            path,
+            CalcQuery {
+                op: Some("product"),
+                transform: Some("mod"),
+                arg: Some("7"),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        // -60 mod 7 is always non-negative
+        assert_eq!(result, "3".to_string());
+    }
+
+    #[test]
+    fn test_min_max_identity() {
+        let path = PathBuf::from("4/-5/8/10");
+        let min = calculate(
+            path.clone(),
+            CalcQuery {
+                op: Some("min"),
+                transform: Some("identity"),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let max = calculate(
+            path,
+            CalcQuery {
+                op: Some("max"),
+                transform: Some("identity"),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(min, "-5".to_string());
+        assert_eq!(max, "10".to_string());
+    }
+
+    #[test]
+    fn test_and_or() {
+        let path = PathBuf::from("12/10");
+        let and = calculate(
+            path.clone(),
+            CalcQuery {
+                op: Some("and"),
+                transform: Some("identity"),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        let or = calculate(
+            path,
+            CalcQuery {
+                op: Some("or"),
+                transform: Some("identity"),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(and, "8".to_string());
+        assert_eq!(or, "14".to_string());
+    }
+
+    #[test]
+    fn test_min_without_packets_bad() {
+        let path = PathBuf::from("");
+        let result = calculate(
+            path,
+            CalcQuery {
+                op: Some("min"),
+                ..Default::default()
+            },
+        );
+        assert_eq!(result, Err(Status::BadRequest));
+    }
+
+    #[test]
+    fn test_unknown_operator_bad() {
+        let path = PathBuf::from("1/2");
+        assert_eq!(
+            calculate(
+                path.clone(),
+                CalcQuery {
+                    op: Some("nand"),
+                    ..Default::default()
+                }
+            ),
+            Err(Status::BadRequest)
+        );
+        assert_eq!(
+            calculate(
+                path.clone(),
+                CalcQuery {
+                    transform: Some("sqrt"),
+                    ..Default::default()
+                }
+            ),
+            Err(Status::BadRequest)
+        );
+        assert_eq!(
+            calculate(
+                path,
+                CalcQuery {
+                    transform: Some("mod"),
+                    arg: Some("0"),
+                    ..Default::default()
+                }
+            ),
+            Err(Status::BadRequest)
+        );
+    }
+
+    #[test]
+    fn test_sum_overflow_bad() {
+        let path = PathBuf::from("9223372036854775807/1");
+        let result = calculate(
+            path,
+            CalcQuery {
+                op: Some("sum"),
+                transform: Some("identity"),
+                ..Default::default()
+            },
+        );
+        assert_eq!(result, Err(Status::BadRequest));
+    }
+
+    #[test]
+    fn test_routes_with_query() {
+        let rocket = rocket::build().mount("/1", routes());
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+
+        let response = client.get("/1/4/5/8/10").dispatch();
+        assert_eq!(response.into_string().unwrap(), "27");
+
+        let response = client.get("/1/1/2/3?op=sum&transform=mod&arg=4").dispatch();
+        assert_eq!(response.into_string().unwrap(), "2");
+
+        let response = client.get("/1/big/18446744073709551616/1").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    #[test]
+    fn test_expr_route() {
+        let rocket = rocket::build().mount("/1", routes());
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+
+        let response = client
+            .post("/1/expr")
+            .body("(4 ^ 5) ** 3 + 8 & 12")
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_string().unwrap(), "8");
+
+        let response = client.post("/1/expr").body("1 / 0").dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+        assert_eq!(
+            response.into_string().unwrap(),
+            r#"{"error":"Division by zero","offset":2}"#
+        );
+
+        // Fits in the default 8 KiB body limit, and used to overflow the worker's stack
+        let deep = format!("{}1{}", "(".repeat(4000), ")".repeat(4000));
+        let response = client.post("/1/expr").body(deep).dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+        assert_eq!(
+            response.into_string().unwrap(),
+            r#"{"error":"Expression is nested too deeply","offset":256}"#
+        );
+    }
+
+    #[test]
+    fn test_batch_per_item_errors() {
+        let items = vec![
+            vec![json!(4), json!(5), json!(8), json!(10)],
+            vec![json!("1"), json!("2"), json!("abc")],
+            vec![json!(0); 21],
+            vec![json!(3000000)],
+            vec![json!("-3")],
+            vec![json!(1e30)],
+            vec![json!(u64::MAX)],
+            vec![json!(2), json!("99999999999999999999")],
+            vec![json!(1.5)],
+        ];
+        let results = batch(Json(items)).into_inner();
+
+        assert_eq!(
+            results,
+            vec![
+                BatchResult::Cube { cube: 27 },
+                BatchResult::Error {
+                    error: PacketError::ParseFailure { packet: 2 }
+                },
+                BatchResult::Error {
+                    error: PacketError::TooManyPackets { count: 21, max: 20 }
+                },
+                BatchResult::Error {
+                    error: PacketError::Overflow
+                },
+                BatchResult::Cube { cube: -27 },
+                BatchResult::Error {
+                    error: PacketError::Overflow
+                },
+                BatchResult::Error {
+                    error: PacketError::Overflow
+                },
+                BatchResult::Error {
+                    error: PacketError::Overflow
+                },
+                BatchResult::Error {
+                    error: PacketError::ParseFailure { packet: 0 }
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_batch_route() {
+        let rocket = rocket::build().mount("/1", routes());
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+
+        let response = client
+            .post("/1/batch")
+            .body(r#"[[4, 5, 8, 10], [1, true], []]"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(
+            response.into_string().unwrap(),
+            r#"[{"cube":27},{"error":{"kind":"parse_failure","packet":1}},{"cube":0}]"#
+        );
+    }
+
+    #[test]
+    fn test_mixed_radix_path() {
+        // 0x1f ^ 0b1010 ^ 0o7 ^ 4 = 31 ^ 10 ^ 7 ^ 4 = 22
+        let path = PathBuf::from("0x1f/0b1010/0o7/4");
+        let result = calculate(path, CalcQuery::default()).unwrap();
+        assert_eq!(result, "10648".to_string());
+    }
+
+    #[test]
+    fn test_negative_hex_packet() {
+        let path = PathBuf::from("-0x10");
+        let query = CalcQuery {
+            transform: Some("identity"),
+            ..Default::default()
+        };
+        assert_eq!(calculate(path, query), Ok("-16".to_string()));
+    }
+
+    #[test]
+    fn test_radix_override() {
+        let path = PathBuf::from("1f/0x01/a");
+        let query = CalcQuery {
+            transform: Some("identity"),
+            radix: Some("16"),
+            ..Default::default()
+        };
+        // 0x1f ^ 0x01 ^ 0xa = 0x14
+        assert_eq!(calculate(path, query), Ok("20".to_string()));
+    }
+
+    #[test]
+    fn test_radix_other_prefix_is_digits() {
+        let path = PathBuf::from("0b101");
+        let query = CalcQuery {
+            transform: Some("identity"),
+            radix: Some("16"),
+            ..Default::default()
+        };
+        assert_eq!(calculate(path, query), Ok(0xb101.to_string()));
+
+        // Unless its digits aren't valid in the requested radix
+        let path = PathBuf::from("0x1f");
+        let query = CalcQuery {
+            radix: Some("8"),
+            ..Default::default()
+        };
+        assert_eq!(calculate(path, query), Err(Status::BadRequest));
+    }
+
+    #[test]
+    fn test_invalid_radix_bad() {
+        for radix in ["1", "37", "hex"] {
+            let query = CalcQuery {
+                output_radix: Some(radix),
+                ..Default::default()
+            };
+            assert_eq!(
+                calculate(PathBuf::from("1"), query),
+                Err(Status::BadRequest)
+            );
+        }
+    }
+
+    #[test]
+    fn test_output_radix() {
+        let path = PathBuf::from("0b11/0x4");
+        let query = CalcQuery {
+            transform: Some("identity"),
+            output_radix: Some("2"),
+            ..Default::default()
+        };
+        assert_eq!(calculate(path, query), Ok("111".to_string()));
+
+        let path = PathBuf::from("-255");
+        let query = CalcQuery {
+            transform: Some("identity"),
+            output_radix: Some("16"),
+            ..Default::default()
+        };
+        assert_eq!(calculate(path, query), Ok("-ff".to_string()));
+    }
+
+    #[test]
+    fn test_format_radix_extremes() {
+        assert_eq!(format_radix(0, 2), "0");
+        assert_eq!(
+            format_radix(isize::MIN, 16),
+            format!("-{:x}", isize::MIN.unsigned_abs())
+        );
+        assert_eq!(format_radix(35, 36), "z");
+    }
+
+    #[test]
+    fn test_double_sign_bad() {
+        assert!(parse_os_str_to_i32(OsStr::new("0x-5")).is_err());
+        assert!(parse_os_str_to_i32(OsStr::new("-+5")).is_err());
+        assert_eq!(parse_os_str_to_i32(OsStr::new("+0b11")), Ok(3));
+    }
+
+    #[test]
+    fn test_radix_query_route() {
+        let rocket = rocket::build().mount("/1", routes());
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+
+        // 0x1f ^ 0xb1010
+        let response = client
+            .get("/1/0x1f/0b1010?radix=16&transform=identity&output_radix=16")
+            .dispatch();
+        assert_eq!(response.into_string().unwrap(), "b100f");
+
+        let response = client
+            .get("/1/1f/a?radix=16&transform=identity&output_radix=16")
+            .dispatch();
+        assert_eq!(response.into_string().unwrap(), "15");
+    }
 }
@@ -0,0 +1,496 @@
+use super::parse_os_str_to_i32;
+use std::ffi::OsStr;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ExprError {
+    #[error("Unexpected character '{ch}'")]
+    UnexpectedChar { offset: usize, ch: char },
+
+    #[error("Invalid number '{literal}'")]
+    InvalidNumber { offset: usize, literal: String },
+
+    #[error("Unexpected token '{token}'")]
+    UnexpectedToken { offset: usize, token: String },
+
+    #[error("Unexpected end of expression")]
+    UnexpectedEnd { offset: usize },
+
+    #[error("Arithmetic overflow")]
+    Overflow { offset: usize },
+
+    #[error("Division by zero")]
+    DivisionByZero { offset: usize },
+
+    #[error("Negative exponent")]
+    NegativeExponent { offset: usize },
+
+    #[error("Expression is nested too deeply")]
+    TooDeep { offset: usize },
+}
+
+impl ExprError {
+    // Byte offset into the expression of the token that caused the error
+    pub fn offset(&self) -> usize {
+        match self {
+            ExprError::UnexpectedChar { offset, .. }
+            | ExprError::InvalidNumber { offset, .. }
+            | ExprError::UnexpectedToken { offset, .. }
+            | ExprError::UnexpectedEnd { offset }
+            | ExprError::Overflow { offset }
+            | ExprError::DivisionByZero { offset }
+            | ExprError::NegativeExponent { offset }
+            | ExprError::TooDeep { offset } => *offset,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Num(isize),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Pow,
+    Amp,
+    Caret,
+    Pipe,
+    LParen,
+    RParen,
+    End,
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    offset: usize,
+    len: usize,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ExprError> {
+    let bytes = input.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        let c = bytes[pos];
+        if c.is_ascii_whitespace() {
+            pos += 1;
+            continue;
+        }
+
+        // Number literals run until the next non-alphanumeric character and are parsed like path packets
+        if c.is_ascii_digit() {
+            let start = pos;
+            while pos < bytes.len() && bytes[pos].is_ascii_alphanumeric() {
+                pos += 1;
+            }
+            let literal = &input[start..pos];
+            let value =
+                parse_os_str_to_i32(OsStr::new(literal)).map_err(|_| ExprError::InvalidNumber {
+                    offset: start,
+                    literal: literal.to_string(),
+                })?;
+            tokens.push(Token {
+                kind: TokenKind::Num(value),
+                offset: start,
+                len: pos - start,
+            });
+            continue;
+        }
+
+        let (kind, len) = match c {
+            b'*' if bytes.get(pos + 1) == Some(&b'*') => (TokenKind::Pow, 2),
+            b'*' => (TokenKind::Star, 1),
+            b'+' => (TokenKind::Plus, 1),
+            b'-' => (TokenKind::Minus, 1),
+            b'/' => (TokenKind::Slash, 1),
+            b'%' => (TokenKind::Percent, 1),
+            b'&' => (TokenKind::Amp, 1),
+            b'^' => (TokenKind::Caret, 1),
+            b'|' => (TokenKind::Pipe, 1),
+            b'(' => (TokenKind::LParen, 1),
+            b')' => (TokenKind::RParen, 1),
+            _ => {
+                let ch = input[pos..].chars().next().unwrap_or_default();
+                return Err(ExprError::UnexpectedChar { offset: pos, ch });
+            }
+        };
+        tokens.push(Token {
+            kind,
+            offset: pos,
+            len,
+        });
+        pos += len;
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::End,
+        offset: input.len(),
+        len: 0,
+    });
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Pow,
+    And,
+    Xor,
+    Or,
+}
+
+impl BinOp {
+    fn from_token(kind: &TokenKind) -> Option<BinOp> {
+        match kind {
+            TokenKind::Plus => Some(BinOp::Add),
+            TokenKind::Minus => Some(BinOp::Sub),
+            TokenKind::Star => Some(BinOp::Mul),
+            TokenKind::Slash => Some(BinOp::Div),
+            TokenKind::Percent => Some(BinOp::Rem),
+            TokenKind::Pow => Some(BinOp::Pow),
+            TokenKind::Amp => Some(BinOp::And),
+            TokenKind::Caret => Some(BinOp::Xor),
+            TokenKind::Pipe => Some(BinOp::Or),
+            _ => None,
+        }
+    }
+
+    // (left, right) binding power, lowest to highest: | ^ & +- */% unary- **
+    fn binding_power(&self) -> (u8, u8) {
+        match self {
+            BinOp::Or => (1, 2),
+            BinOp::Xor => (3, 4),
+            BinOp::And => (5, 6),
+            BinOp::Add | BinOp::Sub => (7, 8),
+            BinOp::Mul | BinOp::Div | BinOp::Rem => (9, 10),
+            // Right associative
+            BinOp::Pow => (14, 13),
+        }
+    }
+}
+
+// Unary minus binds tighter than * but looser than **, so -2 ** 2 == -4
+const UNARY_BINDING_POWER: u8 = 11;
+
+#[derive(Debug, PartialEq)]
+pub enum Expr {
+    Num(isize),
+    Neg {
+        offset: usize,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: BinOp,
+        offset: usize,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+}
+
+// Parsing and evaluating both recurse once per level, so without a limit a body full of
+// parentheses (or a long chain of operators) can overflow the stack and take the server with it
+const MAX_DEPTH: usize = 256;
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+    // How many nested parse calls we're inside
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos]
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.tokens[self.pos].clone();
+        if token.kind != TokenKind::End {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn unexpected(&self, token: &Token) -> ExprError {
+        match token.kind {
+            TokenKind::End => ExprError::UnexpectedEnd {
+                offset: token.offset,
+            },
+            _ => ExprError::UnexpectedToken {
+                offset: token.offset,
+                token: self.input[token.offset..token.offset + token.len].to_string(),
+            },
+        }
+    }
+
+    // Runs a nested parse one level deeper, refusing to go past MAX_DEPTH
+    fn nested<T>(
+        &mut self,
+        offset: usize,
+        parse: impl FnOnce(&mut Self) -> Result<T, ExprError>,
+    ) -> Result<T, ExprError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(ExprError::TooDeep { offset });
+        }
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    // Operator chains like `1+1+1` are built in a loop rather than by recursion, so the height
+    // of the tree has to be checked as well as the depth of the parse
+    fn check_height(&self, height: usize, offset: usize) -> Result<usize, ExprError> {
+        if self.depth + height > MAX_DEPTH {
+            return Err(ExprError::TooDeep { offset });
+        }
+        Ok(height)
+    }
+
+    // Returns the expression along with the height of its tree
+    fn parse_expr(&mut self, min_bp: u8) -> Result<(Expr, usize), ExprError> {
+        let (mut lhs, mut height) = self.parse_unary()?;
+
+        loop {
+            let token = self.peek();
+            let op = match BinOp::from_token(&token.kind) {
+                Some(op) => op,
+                None => break,
+            };
+            let (l_bp, r_bp) = op.binding_power();
+            if l_bp < min_bp {
+                break;
+            }
+
+            let offset = self.next().offset;
+            let (rhs, rhs_height) = self.nested(offset, |parser| parser.parse_expr(r_bp))?;
+            height = self.check_height(height.max(rhs_height) + 1, offset)?;
+            lhs = Expr::Binary {
+                op,
+                offset,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
+        }
+
+        Ok((lhs, height))
+    }
+
+    fn parse_unary(&mut self) -> Result<(Expr, usize), ExprError> {
+        if self.peek().kind == TokenKind::Minus {
+            let offset = self.next().offset;
+            let (operand, height) =
+                self.nested(offset, |parser| parser.parse_expr(UNARY_BINDING_POWER))?;
+            let neg = Expr::Neg {
+                offset,
+                operand: Box::new(operand),
+            };
+            return Ok((neg, self.check_height(height + 1, offset)?));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<(Expr, usize), ExprError> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Num(value) => Ok((Expr::Num(value), 0)),
+            TokenKind::LParen => {
+                let inner = self.nested(token.offset, |parser| parser.parse_expr(0))?;
+                let closing = self.next();
+                if closing.kind != TokenKind::RParen {
+                    return Err(self.unexpected(&closing));
+                }
+                Ok(inner)
+            }
+            _ => Err(self.unexpected(&token)),
+        }
+    }
+}
+
+pub fn parse(input: &str) -> Result<Expr, ExprError> {
+    let mut parser = Parser {
+        input,
+        tokens: tokenize(input)?,
+        pos: 0,
+        depth: 0,
+    };
+    let (expr, _) = parser.parse_expr(0)?;
+
+    // Anything left over (like a stray closing paren) is an error
+    let trailing = parser.next();
+    if trailing.kind != TokenKind::End {
+        return Err(parser.unexpected(&trailing));
+    }
+    Ok(expr)
+}
+
+pub fn eval(expr: &Expr) -> Result<isize, ExprError> {
+    match expr {
+        Expr::Num(value) => Ok(*value),
+        Expr::Neg { offset, operand } => eval(operand)?
+            .checked_neg()
+            .ok_or(ExprError::Overflow { offset: *offset }),
+        Expr::Binary {
+            op,
+            offset,
+            lhs,
+            rhs,
+        } => {
+            let offset = *offset;
+            let lhs = eval(lhs)?;
+            let rhs = eval(rhs)?;
+            let overflow = ExprError::Overflow { offset };
+
+            match op {
+                BinOp::Add => lhs.checked_add(rhs).ok_or(overflow),
+                BinOp::Sub => lhs.checked_sub(rhs).ok_or(overflow),
+                BinOp::Mul => lhs.checked_mul(rhs).ok_or(overflow),
+                BinOp::Div | BinOp::Rem if rhs == 0 => Err(ExprError::DivisionByZero { offset }),
+                BinOp::Div => lhs.checked_div(rhs).ok_or(overflow),
+                BinOp::Rem => lhs.checked_rem(rhs).ok_or(overflow),
+                BinOp::Pow if rhs < 0 => Err(ExprError::NegativeExponent { offset }),
+                BinOp::Pow => u32::try_from(rhs)
+                    .ok()
+                    .and_then(|exp| lhs.checked_pow(exp))
+                    .ok_or(overflow),
+                BinOp::And => Ok(lhs & rhs),
+                BinOp::Xor => Ok(lhs ^ rhs),
+                BinOp::Or => Ok(lhs | rhs),
+            }
+        }
+    }
+}
+
+pub fn evaluate(input: &str) -> Result<isize, ExprError> {
+    eval(&parse(input)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_precedence() {
+        assert_eq!(evaluate("(4 ^ 5) ** 3 + 8 & 12"), Ok(8));
+        assert_eq!(evaluate("1 + 2 * 3"), Ok(7));
+        assert_eq!(evaluate("1 | 2 ^ 3 & 6"), Ok(1));
+        assert_eq!(evaluate("10 - 4 - 3"), Ok(3));
+    }
+
+    #[test]
+    fn test_pow_is_right_associative() {
+        assert_eq!(evaluate("2 ** 3 ** 2"), Ok(512));
+    }
+
+    #[test]
+    fn test_unary_minus() {
+        assert_eq!(evaluate("-2 ** 2"), Ok(-4));
+        assert_eq!(evaluate("(-2) ** 2"), Ok(4));
+        assert_eq!(evaluate("3 * -2"), Ok(-6));
+        assert_eq!(evaluate("--5"), Ok(5));
+    }
+
+    #[test]
+    fn test_ast() {
+        assert_eq!(
+            parse("1-2"),
+            Ok(Expr::Binary {
+                op: BinOp::Sub,
+                offset: 1,
+                lhs: Box::new(Expr::Num(1)),
+                rhs: Box::new(Expr::Num(2)),
+            })
+        );
+    }
+
+    #[test]
+    fn test_error_offsets() {
+        assert_eq!(
+            evaluate("1 + $"),
+            Err(ExprError::UnexpectedChar { offset: 4, ch: '$' })
+        );
+        assert_eq!(
+            evaluate("1 + * 2"),
+            Err(ExprError::UnexpectedToken {
+                offset: 4,
+                token: "*".to_string()
+            })
+        );
+        assert_eq!(
+            evaluate("(1 + 2"),
+            Err(ExprError::UnexpectedEnd { offset: 6 })
+        );
+        assert_eq!(
+            evaluate("1 + 2)"),
+            Err(ExprError::UnexpectedToken {
+                offset: 5,
+                token: ")".to_string()
+            })
+        );
+        assert_eq!(
+            evaluate("12abc"),
+            Err(ExprError::InvalidNumber {
+                offset: 0,
+                literal: "12abc".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_checked_arithmetic() {
+        assert_eq!(
+            evaluate("7 / (3 - 3)"),
+            Err(ExprError::DivisionByZero { offset: 2 })
+        );
+        assert_eq!(evaluate("2 ** 64"), Err(ExprError::Overflow { offset: 2 }));
+        assert_eq!(
+            evaluate("2 ** -1"),
+            Err(ExprError::NegativeExponent { offset: 2 })
+        );
+        assert_eq!(
+            evaluate("9223372036854775807 + 1").unwrap_err().offset(),
+            20
+        );
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(evaluate(&nested(MAX_DEPTH)), Ok(1));
+        assert_eq!(
+            evaluate(&nested(10_000)),
+            Err(ExprError::TooDeep { offset: MAX_DEPTH })
+        );
+
+        assert_eq!(evaluate(&format!("{}1", "-".repeat(MAX_DEPTH))), Ok(1));
+        assert_eq!(
+            evaluate(&format!("{}1", "-".repeat(10_000))),
+            Err(ExprError::TooDeep { offset: MAX_DEPTH })
+        );
+
+        // Chains don't recurse while parsing, but their trees are just as deep to evaluate
+        let chain = |terms: usize| format!("1{}", "+1".repeat(terms - 1));
+        assert_eq!(evaluate(&chain(MAX_DEPTH + 1)), Ok(257));
+        assert_eq!(
+            evaluate(&chain(4_000)),
+            Err(ExprError::TooDeep {
+                offset: 2 * MAX_DEPTH + 1
+            })
+        );
+        assert_eq!(
+            evaluate(&format!("({})", chain(MAX_DEPTH + 1)))
+                .unwrap_err()
+                .offset(),
+            512
+        );
+    }
+}
@@ -15,13 +15,19 @@ pub struct Day12State {
     pub persist: PersistInstance,
 }
 
+// Every persist instance shares one directory, so packets get their own prefix and can't
+// overwrite another day's state
+fn packet_key(packet_id: &str) -> String {
+    format!("day12_{packet_id}")
+}
+
 #[post("/save/<packet_id>")]
 pub async fn save(packet_id: String, state: &State<Day12State>) -> Result<(), Status> {
     let now = Utc::now();
     let now = now.timestamp().to_string();
     println!("@save {packet_id}={}", now);
 
-    match state.persist.save(&packet_id, now.clone()) {
+    match state.persist.save(&packet_key(&packet_id), now.clone()) {
         Ok(_) => Ok(()),
         Err(PersistError::InvalidKey) => Err(Status::BadRequest),
         Err(e) => {
@@ -33,7 +39,7 @@ pub async fn save(packet_id: String, state: &State<Day12State>) -> Result<(), St
 
 #[get("/load/<packet_id>")]
 pub async fn load(packet_id: String, state: &State<Day12State>) -> Result<String, Status> {
-    match state.persist.load::<String>(&packet_id) {
+    match state.persist.load::<String>(&packet_key(&packet_id)) {
         Ok(prev) => {
             let now = Utc::now();
             let now_as_seconds = now.timestamp();
@@ -1,21 +1,67 @@
 use rocket::http::Status;
-use rocket::serde::json::Json;
-use rocket::serde::Deserialize;
-use rocket::{post, routes};
+use rocket::response::status::{BadRequest, Created};
+use rocket::serde::json::{serde_json, Json};
+use rocket::serde::{Deserialize, Serialize};
+use rocket::{delete, get, post, put, routes, State};
+use shuttle_persist::{PersistError, PersistInstance};
 use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::sync::Mutex;
+
+mod elo;
+mod ingest;
+mod race;
+mod stats;
+mod team;
 
 pub fn routes() -> Vec<rocket::Route> {
-    routes![contest, strength]
+    routes![
+        contest_json,
+        contest_rows,
+        leaderboard,
+        strength,
+        strength_rows,
+        roster_list,
+        roster_create,
+        roster_read,
+        roster_update,
+        roster_delete,
+        roster_strength,
+        roster_contest,
+        optimize_team,
+        herd_stats,
+        run_race,
+        ratings,
+        history
+    ]
+}
+
+pub struct Day4State {
+    pub persist: PersistInstance,
+    // Serializes the load-modify-save cycle of roster writes
+    roster_lock: Mutex<()>,
+    // Same for the contest log, which every contest appends to
+    history_lock: Mutex<()>,
+}
+
+impl Day4State {
+    pub fn new(persist: PersistInstance) -> Self {
+        Day4State {
+            persist,
+            roster_lock: Mutex::new(()),
+            history_lock: Mutex::new(()),
+        }
+    }
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, Serialize)]
 #[serde(crate = "rocket::serde")]
 struct Reindeer {
     name: String,
     strength: usize,
 }
 
-#[derive(Clone, Deserialize)]
+#[derive(Clone, Deserialize, Serialize)]
 #[serde(crate = "rocket::serde")]
 struct FullReindeer {
     #[serde(flatten)]
@@ -31,69 +77,896 @@ struct FullReindeer {
     candies_eaten_yesterday: usize,
 }
 
-#[post("/strength", data = "<reindeers>")]
+#[post("/strength", data = "<reindeers>", rank = 2)]
 fn strength(reindeers: Json<Vec<Reindeer>>) -> Json<usize> {
     Json(reindeers.iter().fold(0, |acc, r| acc + r.strength))
 }
 
-#[post("/contest", data = "<reindeers>")]
-fn contest(reindeers: Json<Vec<FullReindeer>>) -> Result<Json<HashMap<String, String>>, Status> {
-    if reindeers.len() == 0 {
-        return Err(Status::BadRequest);
+// CSV and NDJSON herds are summed as they stream in
+#[post("/strength", data = "<rows>", rank = 1)]
+async fn strength_rows(mut rows: ingest::RowStream<'_>) -> Result<Json<usize>, Status> {
+    let mut total = 0;
+    while let Some(reindeer) = rows.next::<Reindeer>().await? {
+        total += reindeer.strength;
     }
+    Ok(Json(total))
+}
 
-    let fastest = reindeers
-        .iter()
-        .max_by(|a, b| {
-            a.speed
-                .partial_cmp(&b.speed)
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+enum Field {
+    Strength,
+    Speed,
+    Height,
+    AntlerWidth,
+    SnowMagicPower,
+    #[serde(alias = "cAnD13s_3ATeN-yesT3rdAy")]
+    CandiesEatenYesterday,
+}
+
+impl Field {
+    const ALL: [Field; 6] = [
+        Field::Strength,
+        Field::Speed,
+        Field::Height,
+        Field::AntlerWidth,
+        Field::SnowMagicPower,
+        Field::CandiesEatenYesterday,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Field::Strength => "strength",
+            Field::Speed => "speed",
+            Field::Height => "height",
+            Field::AntlerWidth => "antler_width",
+            Field::SnowMagicPower => "snow_magic_power",
+            Field::CandiesEatenYesterday => "candies_eaten_yesterday",
+        }
+    }
+}
+
+// Only values of the same field are ever compared, so Integer vs Float ordering never matters
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Serialize)]
+#[serde(crate = "rocket::serde", untagged)]
+enum FieldValue {
+    Integer(usize),
+    Float(f32),
+}
+
+impl FieldValue {
+    fn as_f64(&self) -> f64 {
+        match self {
+            FieldValue::Integer(v) => *v as f64,
+            FieldValue::Float(v) => *v as f64,
+        }
+    }
+}
+
+impl FullReindeer {
+    fn value(&self, field: Field) -> FieldValue {
+        match field {
+            Field::Strength => FieldValue::Integer(self.base.strength),
+            Field::Speed => FieldValue::Float(self.speed),
+            Field::Height => FieldValue::Integer(self.height),
+            Field::AntlerWidth => FieldValue::Integer(self.antler_width),
+            Field::SnowMagicPower => FieldValue::Integer(self.snow_magic_power),
+            Field::CandiesEatenYesterday => FieldValue::Integer(self.candies_eaten_yesterday),
+        }
+    }
+
+    // Values available to `{placeholder}`s in category message templates
+    fn placeholder(&self, name: &str) -> Option<String> {
+        match name {
+            "name" => Some(self.base.name.clone()),
+            "strength" => Some(self.base.strength.to_string()),
+            "speed" => Some(self.speed.to_string()),
+            "height" => Some(self.height.to_string()),
+            "antler_width" => Some(self.antler_width.to_string()),
+            "snow_magic_power" => Some(self.snow_magic_power.to_string()),
+            "favorite_food" => Some(self.favorite_food.clone()),
+            "candies_eaten_yesterday" => Some(self.candies_eaten_yesterday.to_string()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+enum Order {
+    Min,
+    #[default]
+    Max,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct Category {
+    name: String,
+    field: Field,
+    #[serde(default)]
+    order: Order,
+    message: String,
+}
+
+impl Category {
+    fn new(name: &str, field: Field, message: &str) -> Category {
+        Category {
+            name: name.to_string(),
+            field,
+            order: Order::Max,
+            message: message.to_string(),
+        }
+    }
+
+    // Ties go to the last reindeer for max and the first for min, same as `Iterator::max_by`/`min_by`
+    fn winner<'a>(&self, reindeers: &'a [FullReindeer]) -> Option<&'a FullReindeer> {
+        let compare = |a: &&FullReindeer, b: &&FullReindeer| {
+            a.value(self.field)
+                .partial_cmp(&b.value(self.field))
                 .unwrap_or(std::cmp::Ordering::Less)
-        })
-        .unwrap();
-    let tallest = reindeers.iter().max_by_key(|r| r.height).unwrap();
-    let magician = reindeers.iter().max_by_key(|r| r.snow_magic_power).unwrap();
-    let consumer = reindeers
-        .iter()
-        .max_by_key(|r| r.candies_eaten_yesterday)
-        .unwrap();
-
-    let result = vec![
-        (
-            "fastest".to_string(),
-            format!(
-                "Speeding past the finish line with a strength of {} is {}",
-                fastest.base.strength, fastest.base.name
-            ),
+        };
+        match self.order {
+            Order::Max => reindeers.iter().max_by(compare),
+            Order::Min => reindeers.iter().min_by(compare),
+        }
+    }
+}
+
+fn default_categories() -> Vec<Category> {
+    vec![
+        Category::new(
+            "fastest",
+            Field::Speed,
+            "Speeding past the finish line with a strength of {strength} is {name}",
         ),
-        (
-            "tallest".to_string(),
-            format!(
-                "{} is standing tall with his {} cm wide antlers",
-                tallest.base.name, tallest.antler_width
-            ),
+        Category::new(
+            "tallest",
+            Field::Height,
+            "{name} is standing tall with his {antler_width} cm wide antlers",
         ),
-        (
-            "magician".to_string(),
-            format!(
-                "{} could blast you away with a snow magic power of {}",
-                magician.base.name, magician.snow_magic_power
-            ),
+        Category::new(
+            "magician",
+            Field::SnowMagicPower,
+            "{name} could blast you away with a snow magic power of {snow_magic_power}",
         ),
-        (
-            "consumer".to_string(),
-            format!(
-                "{} ate lots of candies, but also some {}",
-                consumer.base.name, consumer.favorite_food
-            ),
+        Category::new(
+            "consumer",
+            Field::CandiesEatenYesterday,
+            "{name} ate lots of candies, but also some {favorite_food}",
         ),
-    ];
+    ]
+}
+
+// Replaces every `{placeholder}` in the template; unknown placeholders are an error
+fn render_message(template: &str, reindeer: &FullReindeer) -> Result<String, String> {
+    let mut result = String::new();
+    let mut rest = template;
+
+    while let Some(open) = rest.find('{') {
+        result.push_str(&rest[..open]);
+        let close = rest[open..]
+            .find('}')
+            .ok_or_else(|| format!("Unclosed placeholder in {template}"))?;
+        let name = &rest[open + 1..open + close];
+        let value = reindeer
+            .placeholder(name)
+            .ok_or_else(|| format!("Unknown placeholder {{{name}}} in {template}"))?;
+        result.push_str(&value);
+        rest = &rest[open + close + 1..];
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+// Either the plain herd, or the herd plus custom category definitions
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde", untagged)]
+enum ContestRequest {
+    Herd(Vec<FullReindeer>),
+    WithCategories {
+        reindeers: Vec<FullReindeer>,
+        categories: Option<Vec<Category>>,
+    },
+}
+
+impl ContestRequest {
+    fn into_parts(self) -> (Vec<FullReindeer>, Vec<Category>) {
+        match self {
+            ContestRequest::Herd(reindeers) => (reindeers, default_categories()),
+            ContestRequest::WithCategories {
+                reindeers,
+                categories,
+            } => (reindeers, categories.unwrap_or_else(default_categories)),
+        }
+    }
+}
+
+// Messages and winner names, both keyed by category name
+struct Judgement {
+    messages: HashMap<String, String>,
+    winners: HashMap<String, String>,
+}
+
+fn judge(reindeers: &[FullReindeer], categories: Vec<Category>) -> Result<Judgement, Status> {
+    if reindeers.is_empty() {
+        return Err(Status::BadRequest);
+    }
+
+    let mut judgement = Judgement {
+        messages: HashMap::new(),
+        winners: HashMap::new(),
+    };
+    for category in categories {
+        let winner = category.winner(reindeers).ok_or(Status::BadRequest)?;
+        let message = render_message(&category.message, winner).map_err(|e| {
+            println!("Failed to render category {}: {e}", category.name);
+            Status::BadRequest
+        })?;
+        judgement
+            .winners
+            .insert(category.name.clone(), winner.base.name.clone());
+        judgement.messages.insert(category.name, message);
+    }
+
+    Ok(judgement)
+}
+
+// Judges the contest and counts it towards the ratings
+fn contest_recorded(
+    reindeers: Vec<FullReindeer>,
+    categories: Vec<Category>,
+    state: &Day4State,
+) -> Result<Json<HashMap<String, String>>, Status> {
+    let judgement = judge(&reindeers, categories)?;
+    let participants: Vec<String> = reindeers.into_iter().map(|r| r.base.name).collect();
+
+    let mut contest = elo::ContestRecord::new(
+        &participants,
+        &judgement.winners,
+        chrono::Utc::now().to_rfc3339(),
+    );
+
+    let _lock = state.history_lock.lock().unwrap();
+    let mut log = load_contest_log(&state.persist)?;
+    log.record(&mut contest);
+    // The record goes first, so the log never counts a contest that wasn't saved
+    save_contest_record(&state.persist, &contest)?;
+    save_contest_log(&state.persist, &log)?;
+
+    Ok(Json(judgement.messages))
+}
+
+#[post("/contest", data = "<request>", rank = 2)]
+async fn contest_json(
+    request: Json<ContestRequest>,
+    state: &State<Day4State>,
+) -> Result<Json<HashMap<String, String>>, Status> {
+    let (reindeers, categories) = request.into_inner().into_parts();
+    contest_recorded(reindeers, categories, state)
+}
+
+#[post("/contest", data = "<rows>", rank = 1)]
+async fn contest_rows(
+    rows: ingest::RowStream<'_>,
+    state: &State<Day4State>,
+) -> Result<Json<HashMap<String, String>>, Status> {
+    let reindeers = rows.collect::<FullReindeer>().await?;
+    contest_recorded(reindeers, default_categories(), state)
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct FieldError {
+    index: usize,
+    name: String,
+    field: Field,
+    message: String,
+}
+
+// Speed is the only float; NaN or infinite values can't be ranked
+fn validate_herd(reindeers: &[FullReindeer]) -> Result<(), Vec<FieldError>> {
+    let errors: Vec<FieldError> = reindeers
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| !r.speed.is_finite())
+        .map(|(index, r)| FieldError {
+            index,
+            name: r.base.name.clone(),
+            field: Field::Speed,
+            message: format!("speed must be a finite number, got {}", r.speed),
+        })
+        .collect();
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors),
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct HerdError {
+    error: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldError>,
+}
+
+fn herd_error(error: String) -> BadRequest<Json<HerdError>> {
+    BadRequest(Json(HerdError {
+        error,
+        errors: vec![],
+    }))
+}
+
+// validate_herd as a route error
+fn check_herd(reindeers: &[FullReindeer]) -> Result<(), BadRequest<Json<HerdError>>> {
+    validate_herd(reindeers).map_err(|errors| {
+        BadRequest(Json(HerdError {
+            error: "Invalid reindeer".to_string(),
+            errors,
+        }))
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Ranking {
+    // 1, 2, 2, 3
+    Dense,
+    // 1, 2, 2, 4
+    Competition,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct RankGroup {
+    rank: usize,
+    value: FieldValue,
+    reindeers: Vec<String>,
+}
+
+// Best (highest) value first; tied reindeer share a group in herd order
+fn rank_field(reindeers: &[FullReindeer], field: Field, ranking: Ranking) -> Vec<RankGroup> {
+    let mut sorted: Vec<&FullReindeer> = reindeers.iter().collect();
+    sorted.sort_by(|a, b| {
+        b.value(field)
+            .partial_cmp(&a.value(field))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut groups: Vec<RankGroup> = Vec::new();
+    for (position, reindeer) in sorted.into_iter().enumerate() {
+        let value = reindeer.value(field);
+        match groups.last_mut() {
+            Some(group) if group.value == value => group.reindeers.push(reindeer.base.name.clone()),
+            _ => {
+                let rank = match ranking {
+                    Ranking::Dense => groups.len() + 1,
+                    Ranking::Competition => position + 1,
+                };
+                groups.push(RankGroup {
+                    rank,
+                    value,
+                    reindeers: vec![reindeer.base.name.clone()],
+                });
+            }
+        }
+    }
+
+    groups
+}
+
+type Leaderboards = HashMap<String, Vec<RankGroup>>;
+
+#[post("/leaderboard?<ranking>", data = "<reindeers>")]
+fn leaderboard(
+    reindeers: Json<Vec<FullReindeer>>,
+    ranking: Option<&str>,
+) -> Result<Json<Leaderboards>, BadRequest<Json<HerdError>>> {
+    let ranking = match ranking.unwrap_or("competition") {
+        "dense" => Ranking::Dense,
+        "competition" => Ranking::Competition,
+        other => {
+            return Err(herd_error(format!(
+                "Unknown ranking {other}, expected dense or competition"
+            )))
+        }
+    };
+
+    check_herd(&reindeers)?;
+
+    let result = Field::ALL
+        .iter()
+        .map(|field| {
+            (
+                field.name().to_string(),
+                rank_field(&reindeers, *field, ranking),
+            )
+        })
+        .collect();
+
+    Ok(Json(result))
+}
+
+// Comma separated percentiles between 0 and 100
+fn parse_percentiles(raw: &str) -> Result<Vec<f64>, String> {
+    raw.split(',')
+        .map(|p| match p.trim().parse::<f64>() {
+            Ok(p) if (0.0..=100.0).contains(&p) => Ok(p),
+            _ => Err(format!("Invalid percentile {p}, expected 0 to 100")),
+        })
+        .collect()
+}
+
+#[post("/stats?<percentiles>&<buckets>", data = "<request>")]
+fn herd_stats(
+    request: Json<ContestRequest>,
+    percentiles: Option<&str>,
+    buckets: Option<&str>,
+) -> Result<Json<HashMap<String, stats::Summary>>, BadRequest<Json<HerdError>>> {
+    let reindeers = match request.into_inner() {
+        ContestRequest::Herd(reindeers) => reindeers,
+        ContestRequest::WithCategories { reindeers, .. } => reindeers,
+    };
+    if reindeers.is_empty() {
+        return Err(herd_error("No reindeer to describe".to_string()));
+    }
+    check_herd(&reindeers)?;
+
+    let percentiles =
+        parse_percentiles(percentiles.unwrap_or("25,50,75,90")).map_err(herd_error)?;
+    let buckets = match buckets {
+        Some(raw) => match raw.parse::<usize>() {
+            Ok(b) if (1..=1000).contains(&b) => Some(b),
+            _ => return Err(herd_error(format!("Invalid bucket count {raw}"))),
+        },
+        None => None,
+    };
+
+    let result = Field::ALL
+        .iter()
+        .filter_map(|field| {
+            let values: Vec<f64> = reindeers.iter().map(|r| r.value(*field).as_f64()).collect();
+            stats::describe(&values, &percentiles, buckets).map(|s| (field.name().to_string(), s))
+        })
+        .collect();
+
+    Ok(Json(result))
+}
+
+// Caps the simulation at a few million reindeer steps
+const MAX_RACE_STEPS: usize = 5_000_000;
+// Caps the returned timeline at around 2MB of JSON
+const MAX_RACE_POSITIONS: usize = 100_000;
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct RaceRequest {
+    reindeers: Vec<FullReindeer>,
+    distance: f64,
+    #[serde(default)]
+    seed: u64,
+    #[serde(default = "default_max_ticks")]
+    max_ticks: usize,
+    #[serde(default = "default_sample_every")]
+    sample_every: usize,
+}
+
+fn default_max_ticks() -> usize {
+    1000
+}
+
+fn default_sample_every() -> usize {
+    1
+}
+
+#[post("/race", data = "<request>")]
+fn run_race(
+    request: Json<RaceRequest>,
+) -> Result<Json<race::RaceResult>, BadRequest<Json<HerdError>>> {
+    if !request.distance.is_finite() || request.distance <= 0.0 {
+        return Err(herd_error(format!("Invalid distance {}", request.distance)));
+    }
+    if request.sample_every == 0 {
+        return Err(herd_error("sample_every must be at least 1".to_string()));
+    }
+    if request.max_ticks.saturating_mul(request.reindeers.len()) > MAX_RACE_STEPS {
+        return Err(herd_error(format!(
+            "Too many ticks for {} reindeer",
+            request.reindeers.len()
+        )));
+    }
+    let samples = request.max_ticks / request.sample_every + 1;
+    if samples.saturating_mul(request.reindeers.len()) > MAX_RACE_POSITIONS {
+        return Err(herd_error(format!(
+            "Timeline too large for {} reindeer, raise sample_every",
+            request.reindeers.len()
+        )));
+    }
+    check_herd(&request.reindeers)?;
+
+    Ok(Json(race::simulate(
+        &request.reindeers,
+        request.distance,
+        request.seed,
+        request.max_ticks,
+        request.sample_every,
+    )))
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct TeamRequest {
+    reindeers: Vec<FullReindeer>,
+    team_size: usize,
+    max_candies: Option<usize>,
+    min_snow_magic: Option<usize>,
+    max_height_spread: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct TeamResponse {
+    team: Vec<String>,
+    strength: u128,
+    candies: usize,
+    snow_magic: u128,
+    height_spread: usize,
+    // False if the search gave up before proving no better team exists
+    optimal: bool,
+}
+
+// Picks the strongest team of `team_size` that eats at most `max_candies` in total, has at least
+// `min_snow_magic` in total and whose tallest and shortest differ by at most `max_height_spread`
+#[post("/team", data = "<request>")]
+async fn optimize_team(request: Json<TeamRequest>) -> Result<Json<TeamResponse>, Status> {
+    let request = request.into_inner();
+    let constraints = team::Constraints {
+        team_size: request.team_size,
+        max_candies: request.max_candies,
+        min_snow_magic: request.min_snow_magic,
+        max_height_spread: request.max_height_spread,
+    };
+
+    // The search can take a second or so, which mustn't hold up the async workers
+    let (request, team) = tokio::task::spawn_blocking(move || {
+        let team = team::best_team(&request.reindeers, constraints);
+        (request, team)
+    })
+    .await
+    .map_err(|e| {
+        println!("Team search failed: {e}");
+        Status::InternalServerError
+    })?;
+    let team = team.ok_or_else(|| {
+        println!("No team of {} satisfies {constraints:?}", request.team_size);
+        Status::UnprocessableEntity
+    })?;
+
+    let members: Vec<&FullReindeer> = team
+        .members
+        .iter()
+        .map(|&i| &request.reindeers[i])
+        .collect();
+    let tallest = members.iter().map(|r| r.height).max().unwrap_or(0);
+    let shortest = members.iter().map(|r| r.height).min().unwrap_or(0);
+
+    Ok(Json(TeamResponse {
+        team: members.iter().map(|r| r.base.name.clone()).collect(),
+        strength: team.strength,
+        candies: team.candies,
+        snow_magic: team.snow_magic,
+        height_spread: tallest - shortest,
+        optimal: team.optimal,
+    }))
+}
+
+const ROSTER_KEY: &str = "day4_roster";
+
+// GET /roster/strength and /roster/contest win over /roster/<name>, so a reindeer with one of
+// these names could never be read back
+const RESERVED_NAMES: [&str; 2] = ["strength", "contest"];
+
+fn check_roster_name(name: &str) -> Result<(), Status> {
+    if RESERVED_NAMES.contains(&name) {
+        println!("Reindeer can't be called {name}");
+        return Err(Status::BadRequest);
+    }
+    Ok(())
+}
+
+// Stored as a JSON string since the flattened reindeer don't round-trip through the persist encoding
+fn load_roster(persist: &PersistInstance) -> Result<Vec<FullReindeer>, Status> {
+    let json = match persist.load::<String>(ROSTER_KEY) {
+        Ok(json) => json,
+        // Only a roster that was never saved starts empty; saving over one we failed to read
+        // would lose the herd
+        Err(PersistError::Open(e)) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => {
+            println!("Error loading roster: {e}");
+            return Err(Status::InternalServerError);
+        }
+    };
+    serde_json::from_str(&json).map_err(|e| {
+        println!("Failed to parse stored roster {json}: {e}");
+        Status::InternalServerError
+    })
+}
+
+fn save_roster(persist: &PersistInstance, roster: &[FullReindeer]) -> Result<(), Status> {
+    let json = serde_json::to_string(roster).map_err(|e| {
+        println!("Failed to serialize roster: {e}");
+        Status::InternalServerError
+    })?;
+    persist.save(ROSTER_KEY, json).map_err(|e| {
+        println!("Error saving roster: {e}");
+        Status::InternalServerError
+    })
+}
+
+#[get("/roster")]
+async fn roster_list(state: &State<Day4State>) -> Result<Json<Vec<FullReindeer>>, Status> {
+    Ok(Json(load_roster(&state.persist)?))
+}
+
+#[post("/roster", data = "<reindeer>")]
+async fn roster_create(
+    reindeer: Json<FullReindeer>,
+    state: &State<Day4State>,
+) -> Result<Created<Json<FullReindeer>>, Status> {
+    check_roster_name(&reindeer.base.name)?;
+
+    let _lock = state.roster_lock.lock().unwrap();
+    let mut roster = load_roster(&state.persist)?;
+
+    if roster.iter().any(|r| r.base.name == reindeer.base.name) {
+        println!("Reindeer {} is already on the roster", reindeer.base.name);
+        return Err(Status::Conflict);
+    }
+
+    let reindeer = reindeer.into_inner();
+    roster.push(reindeer.clone());
+    save_roster(&state.persist, &roster)?;
+
+    let location = format!("/4/roster/{}", reindeer.base.name);
+    Ok(Created::new(location).body(Json(reindeer)))
+}
+
+#[get("/roster/<name>")]
+async fn roster_read(name: &str, state: &State<Day4State>) -> Result<Json<FullReindeer>, Status> {
+    load_roster(&state.persist)?
+        .into_iter()
+        .find(|r| r.base.name == name)
+        .map(Json)
+        .ok_or(Status::NotFound)
+}
+
+#[put("/roster/<name>", data = "<reindeer>")]
+async fn roster_update(
+    name: &str,
+    reindeer: Json<FullReindeer>,
+    state: &State<Day4State>,
+) -> Result<Json<FullReindeer>, Status> {
+    // The name is the key, so renaming is a delete and create
+    if reindeer.base.name != name {
+        return Err(Status::BadRequest);
+    }
+    check_roster_name(name)?;
+
+    let _lock = state.roster_lock.lock().unwrap();
+    let mut roster = load_roster(&state.persist)?;
+    let existing = roster
+        .iter_mut()
+        .find(|r| r.base.name == name)
+        .ok_or(Status::NotFound)?;
+
+    *existing = reindeer.into_inner();
+    let updated = existing.clone();
+    save_roster(&state.persist, &roster)?;
+
+    Ok(Json(updated))
+}
+
+#[delete("/roster/<name>")]
+async fn roster_delete(name: &str, state: &State<Day4State>) -> Result<(), Status> {
+    let _lock = state.roster_lock.lock().unwrap();
+    let mut roster = load_roster(&state.persist)?;
+
+    let before = roster.len();
+    roster.retain(|r| r.base.name != name);
+    if roster.len() == before {
+        return Err(Status::NotFound);
+    }
+
+    save_roster(&state.persist, &roster)
+}
+
+#[get("/roster/strength")]
+async fn roster_strength(state: &State<Day4State>) -> Result<Json<usize>, Status> {
+    let roster = load_roster(&state.persist)?;
+    Ok(strength(Json(roster.into_iter().map(|r| r.base).collect())))
+}
+
+#[get("/roster/contest")]
+async fn roster_contest(state: &State<Day4State>) -> Result<Json<HashMap<String, String>>, Status> {
+    let roster = load_roster(&state.persist)?;
+    contest_recorded(roster, default_categories(), state)
+}
+
+const CONTEST_LOG_KEY: &str = "day4_contest_log";
+
+// JSON string for the same reason as the roster
+fn load_contest_log(persist: &PersistInstance) -> Result<elo::ContestLog, Status> {
+    let json = match persist.load::<String>(CONTEST_LOG_KEY) {
+        Ok(json) => json,
+        // As with the roster, a log we failed to read must not be replaced by an empty one
+        Err(PersistError::Open(e)) if e.kind() == ErrorKind::NotFound => {
+            return Ok(elo::ContestLog::default())
+        }
+        Err(e) => {
+            println!("Error loading contest log: {e}");
+            return Err(Status::InternalServerError);
+        }
+    };
+    serde_json::from_str(&json).map_err(|e| {
+        println!("Failed to parse stored contest log: {e}");
+        Status::InternalServerError
+    })
+}
+
+fn save_contest_log(persist: &PersistInstance, log: &elo::ContestLog) -> Result<(), Status> {
+    let json = serde_json::to_string(log).map_err(|e| {
+        println!("Failed to serialize contest log: {e}");
+        Status::InternalServerError
+    })?;
+    persist.save(CONTEST_LOG_KEY, json).map_err(|e| {
+        println!("Error saving contest log: {e}");
+        Status::InternalServerError
+    })
+}
+
+// Each contest under its own key, so recording one doesn't rewrite the whole history
+fn contest_record_key(id: usize) -> String {
+    format!("day4_contest_{id}")
+}
+
+// Every id up to the log's count was saved before the log was
+fn load_contest_record(persist: &PersistInstance, id: usize) -> Result<elo::ContestRecord, Status> {
+    let json = persist
+        .load::<String>(&contest_record_key(id))
+        .map_err(|e| {
+            println!("Error loading contest {id}: {e}");
+            Status::InternalServerError
+        })?;
+    serde_json::from_str(&json).map_err(|e| {
+        println!("Failed to parse stored contest {id}: {e}");
+        Status::InternalServerError
+    })
+}
+
+fn save_contest_record(
+    persist: &PersistInstance,
+    record: &elo::ContestRecord,
+) -> Result<(), Status> {
+    let json = serde_json::to_string(record).map_err(|e| {
+        println!("Failed to serialize contest {}: {e}", record.id);
+        Status::InternalServerError
+    })?;
+    persist
+        .save(&contest_record_key(record.id), json)
+        .map_err(|e| {
+            println!("Error saving contest {}: {e}", record.id);
+            Status::InternalServerError
+        })
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct RatingEntry {
+    name: String,
+    #[serde(flatten)]
+    rating: elo::Rating,
+}
+
+// Highest rated first
+#[get("/ratings")]
+async fn ratings(state: &State<Day4State>) -> Result<Json<Vec<RatingEntry>>, Status> {
+    let log = load_contest_log(&state.persist)?;
+    let mut entries: Vec<RatingEntry> = log
+        .ratings
+        .into_iter()
+        .map(|(name, rating)| RatingEntry { name, rating })
+        .collect();
+    entries.sort_by(|a, b| b.rating.rating.total_cmp(&a.rating.rating));
+    Ok(Json(entries))
+}
+
+// One reindeer's rating after each contest it took part in
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct HistoryPoint {
+    contest: usize,
+    timestamp: String,
+    rating: f64,
+    won: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde", untagged)]
+enum History {
+    Contests(Vec<elo::ContestRecord>),
+    Reindeer(Vec<HistoryPoint>),
+}
+
+const DEFAULT_HISTORY_PAGE: usize = 100;
+const MAX_HISTORY_PAGE: usize = 1000;
+
+// Pages through contests in id order: up to `limit` of them (or of the named reindeer's) after
+// contest `after`. Pass the last id of a page as `after` to get the next one.
+#[get("/history?<name>&<after>&<limit>")]
+async fn history(
+    name: Option<&str>,
+    after: Option<usize>,
+    limit: Option<usize>,
+    state: &State<Day4State>,
+) -> Result<Json<History>, Status> {
+    let limit = match limit {
+        Some(limit) if !(1..=MAX_HISTORY_PAGE).contains(&limit) => {
+            println!("History page size must be 1 to {MAX_HISTORY_PAGE}, got {limit}");
+            return Err(Status::BadRequest);
+        }
+        limit => limit.unwrap_or(DEFAULT_HISTORY_PAGE),
+    };
+    let log = load_contest_log(&state.persist)?;
+    let ids = after.unwrap_or(0).saturating_add(1)..=log.contests;
+
+    let name = match name {
+        Some(name) => name,
+        None => {
+            let contests = ids
+                .take(limit)
+                .map(|id| load_contest_record(&state.persist, id))
+                .collect::<Result<Vec<_>, Status>>()?;
+            return Ok(Json(History::Contests(contests)));
+        }
+    };
+    if !log.ratings.contains_key(name) {
+        return Err(Status::NotFound);
+    }
 
-    Ok(Json(result.into_iter().collect()))
+    let mut points = Vec::new();
+    for id in ids {
+        if points.len() == limit {
+            break;
+        }
+        let contest = load_contest_record(&state.persist, id)?;
+        let rating = match contest.ratings.get(name) {
+            Some(&rating) => rating,
+            None => continue,
+        };
+        let won = contest
+            .winners
+            .into_iter()
+            .filter(|(_, winner)| winner == name)
+            .map(|(category, _)| category)
+            .collect();
+        points.push(HistoryPoint {
+            contest: contest.id,
+            timestamp: contest.timestamp,
+            rating,
+            won,
+        });
+    }
+
+    Ok(Json(History::Reindeer(points)))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rocket::http::ContentType;
+    use rocket::local::blocking::Client;
 
     fn mock_reindeer(name: &str, strength: usize) -> Reindeer {
         Reindeer {
@@ -150,10 +1023,73 @@ mod tests {
         }
     }
 
+    fn client() -> Client {
+        let dir = std::env::temp_dir().join(format!("day4-roster-{}", ulid::Ulid::new()));
+        let state = Day4State::new(PersistInstance::new(dir).unwrap());
+        let rocket = rocket::build().mount("/4", routes()).manage(state);
+        Client::tracked(rocket).expect("valid rocket instance")
+    }
+
+    // Day 12 saves packets into the same persist directory
+    fn client_with_day12() -> Client {
+        let dir = std::env::temp_dir().join(format!("day4-roster-{}", ulid::Ulid::new()));
+        let persist = PersistInstance::new(dir).unwrap();
+        let state12 = crate::day12::Day12State {
+            persist: persist.clone(),
+        };
+        let rocket = rocket::build()
+            .mount("/4", routes())
+            .mount("/12", crate::day12::routes())
+            .manage(Day4State::new(persist))
+            .manage(state12);
+        Client::tracked(rocket).expect("valid rocket instance")
+    }
+
+    #[test]
+    fn test_unreadable_roster_is_not_overwritten() {
+        let dir = std::env::temp_dir().join(format!("day4-roster-{}", ulid::Ulid::new()));
+        let persist = PersistInstance::new(dir).unwrap();
+        persist.save(ROSTER_KEY, 7u8).unwrap();
+        let state = Day4State::new(persist.clone());
+        let rocket = rocket::build().mount("/4", routes()).manage(state);
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+
+        let response = client.get("/4/roster").dispatch();
+        assert_eq!(response.status(), Status::InternalServerError);
+        let response = client
+            .post("/4/roster")
+            .body(reindeer_json("Dasher", 5, 50.4))
+            .dispatch();
+        assert_eq!(response.status(), Status::InternalServerError);
+        assert_eq!(persist.load::<u8>(ROSTER_KEY).unwrap(), 7);
+    }
+
+    #[test]
+    fn test_day12_save_does_not_touch_roster() {
+        let client = client_with_day12();
+
+        let response = client
+            .post("/4/roster")
+            .body(reindeer_json("Dasher", 5, 50.4))
+            .dispatch();
+        assert_eq!(response.status(), Status::Created);
+        for packet_id in [ROSTER_KEY, "reindeer_roster"] {
+            let response = client.post(format!("/12/save/{packet_id}")).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+        }
+
+        let response = client.get("/4/roster").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let roster: Vec<FullReindeer> = response.into_json().unwrap();
+        assert_eq!(roster.len(), 1);
+        assert_eq!(roster[0].base.name, "Dasher");
+    }
+
     #[test]
     fn test_contest_empty() {
-        let result = contest(Json(vec![]));
-        assert_eq!(Err(Status::BadRequest), result);
+        let client = client();
+        let response = client.post("/4/contest").body("[]").dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
     }
 
     #[test]
@@ -164,10 +1100,14 @@ mod tests {
             create_full_reindeer("Prancer", 50.0, 145, 30, 250, "Berries", 12),
         ];
 
-        let response = contest(Json(reindeers));
-        assert_eq!(true, response.is_ok());
+        let client = client();
+        let response = client
+            .post("/4/contest")
+            .body(serde_json::to_string(&reindeers).unwrap())
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
 
-        let winners = response.unwrap().into_inner();
+        let winners: HashMap<String, String> = response.into_json().unwrap();
 
         assert_eq!(
             winners.get("fastest").unwrap(),
@@ -186,4 +1126,599 @@ mod tests {
             "Dancer ate lots of candies, but also some Apples"
         );
     }
+
+    #[test]
+    fn test_contest_custom_categories() {
+        let reindeers = vec![
+            create_full_reindeer("Dasher", 55.5, 150, 35, 200, "Carrots", 10),
+            create_full_reindeer("Dancer", 60.0, 140, 40, 300, "Apples", 15),
+            create_full_reindeer("Prancer", 50.0, 145, 30, 250, "Berries", 12),
+        ];
+        let categories = vec![
+            Category {
+                name: "slowest".to_string(),
+                field: Field::Speed,
+                order: Order::Min,
+                message: "{name} trots in at {speed}".to_string(),
+            },
+            Category::new("widest", Field::AntlerWidth, "{name}: {antler_width} cm"),
+        ];
+
+        let winners = judge(&reindeers, categories).unwrap().messages;
+
+        assert_eq!(winners.len(), 2);
+        assert_eq!(winners.get("slowest").unwrap(), "Prancer trots in at 50");
+        assert_eq!(winners.get("widest").unwrap(), "Dancer: 40 cm");
+    }
+
+    #[test]
+    fn test_contest_unknown_placeholder() {
+        let reindeers = vec![create_full_reindeer(
+            "Dasher", 55.5, 150, 35, 200, "Carrots", 10,
+        )];
+        let categories = vec![Category::new("tallest", Field::Height, "{name} {wingspan}")];
+
+        let result = judge(&reindeers, categories).map(|judgement| judgement.messages);
+        assert_eq!(Err(Status::BadRequest), result);
+    }
+
+    #[test]
+    fn test_contest_request_formats() {
+        let client = client();
+        let herd = r#"[{"name":"Dasher","strength":5,"speed":50.4,"height":80,"antler_width":36,"snow_magic_power":9001,"favorite_food":"hay","cAnD13s_3ATeN-yesT3rdAy":2}]"#;
+
+        let response = client.post("/4/contest").body(herd).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let body = format!(
+            r#"{{"reindeers":{herd},"categories":[{{"name":"hungriest","field":"cAnD13s_3ATeN-yesT3rdAy","message":"{{name}} ate {{candies_eaten_yesterday}}"}}]}}"#
+        );
+        let response = client.post("/4/contest").body(body).dispatch();
+        assert_eq!(
+            response.into_string().unwrap(),
+            r#"{"hungriest":"Dasher ate 2"}"#
+        );
+
+        // Without categories the defaults are used
+        let body = format!(r#"{{"reindeers":{herd}}}"#);
+        let response = client.post("/4/contest").body(body).dispatch();
+        let winners: HashMap<String, String> = response.into_json().unwrap();
+        assert_eq!(winners.len(), 4);
+    }
+
+    #[test]
+    fn test_rank_field_ties() {
+        let reindeers = vec![
+            create_full_reindeer("Dasher", 55.5, 150, 35, 200, "Carrots", 10),
+            create_full_reindeer("Dancer", 60.0, 140, 40, 300, "Apples", 15),
+            create_full_reindeer("Prancer", 50.0, 150, 30, 250, "Berries", 12),
+            create_full_reindeer("Vixen", 52.0, 130, 30, 250, "Hay", 12),
+        ];
+
+        let competition = rank_field(&reindeers, Field::Height, Ranking::Competition);
+        assert_eq!(
+            competition,
+            vec![
+                RankGroup {
+                    rank: 1,
+                    value: FieldValue::Integer(150),
+                    reindeers: vec!["Dasher".to_string(), "Prancer".to_string()],
+                },
+                RankGroup {
+                    rank: 3,
+                    value: FieldValue::Integer(140),
+                    reindeers: vec!["Dancer".to_string()],
+                },
+                RankGroup {
+                    rank: 4,
+                    value: FieldValue::Integer(130),
+                    reindeers: vec!["Vixen".to_string()],
+                },
+            ]
+        );
+
+        let dense = rank_field(&reindeers, Field::Height, Ranking::Dense);
+        let ranks: Vec<usize> = dense.iter().map(|g| g.rank).collect();
+        assert_eq!(ranks, vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_rank_field_speed() {
+        let reindeers = vec![
+            create_full_reindeer("Dasher", 55.5, 150, 35, 200, "Carrots", 10),
+            create_full_reindeer("Dancer", 60.0, 140, 40, 300, "Apples", 15),
+        ];
+        let groups = rank_field(&reindeers, Field::Speed, Ranking::Dense);
+        assert_eq!(groups[0].reindeers, vec!["Dancer".to_string()]);
+        assert_eq!(groups[1].value, FieldValue::Float(55.5));
+    }
+
+    #[test]
+    fn test_validate_herd_rejects_non_finite_speed() {
+        let reindeers = vec![
+            create_full_reindeer("Dasher", 55.5, 150, 35, 200, "Carrots", 10),
+            create_full_reindeer("Dancer", f32::NAN, 140, 40, 300, "Apples", 15),
+            create_full_reindeer("Prancer", f32::INFINITY, 145, 30, 250, "Berries", 12),
+        ];
+        let errors = validate_herd(&reindeers).unwrap_err();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].index, 1);
+        assert_eq!(errors[0].field, Field::Speed);
+        assert_eq!(errors[1].name, "Prancer");
+    }
+
+    #[test]
+    fn test_leaderboard_route() {
+        let client = client();
+        let herd = r#"[
+            {"name":"Dasher","strength":5,"speed":50.4,"height":80,"antler_width":36,"snow_magic_power":9001,"favorite_food":"hay","cAnD13s_3ATeN-yesT3rdAy":2},
+            {"name":"Dancer","strength":6,"speed":48.2,"height":65,"antler_width":37,"snow_magic_power":4004,"favorite_food":"grass","cAnD13s_3ATeN-yesT3rdAy":2}
+        ]"#;
+
+        let response = client
+            .post("/4/leaderboard?ranking=dense")
+            .body(herd)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let boards: rocket::serde::json::Value = response.into_json().unwrap();
+        assert_eq!(
+            boards["candies_eaten_yesterday"],
+            rocket::serde::json::json!([{"rank": 1, "value": 2, "reindeers": ["Dasher", "Dancer"]}])
+        );
+
+        let response = client
+            .post("/4/leaderboard?ranking=olympic")
+            .body(herd)
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        // 1e39 doesn't fit in an f32 and becomes infinite
+        let herd = herd.replace("50.4", "1e39");
+        let response = client.post("/4/leaderboard").body(herd).dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+        let error: rocket::serde::json::Value = response.into_json().unwrap();
+        assert_eq!(error["errors"][0]["field"], "speed");
+        assert_eq!(error["errors"][0]["name"], "Dasher");
+    }
+
+    fn reindeer_json(name: &str, strength: usize, speed: f32) -> String {
+        format!(
+            r#"{{"name":"{name}","strength":{strength},"speed":{speed},"height":80,"antler_width":36,"snow_magic_power":9001,"favorite_food":"hay","cAnD13s_3ATeN-yesT3rdAy":2}}"#
+        )
+    }
+
+    #[test]
+    fn test_roster_crud() {
+        let client = client();
+
+        let response = client
+            .post("/4/roster")
+            .body(reindeer_json("Dasher", 5, 50.4))
+            .dispatch();
+        assert_eq!(response.status(), Status::Created);
+
+        let response = client
+            .post("/4/roster")
+            .body(reindeer_json("Dasher", 7, 50.4))
+            .dispatch();
+        assert_eq!(response.status(), Status::Conflict);
+
+        let response = client.get("/4/roster/Dasher").dispatch();
+        assert_eq!(
+            response.into_string().unwrap(),
+            reindeer_json("Dasher", 5, 50.4)
+        );
+
+        let response = client
+            .put("/4/roster/Dasher")
+            .body(reindeer_json("Dasher", 9, 50.4))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client
+            .put("/4/roster/Dasher")
+            .body(reindeer_json("Dancer", 9, 50.4))
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let response = client
+            .put("/4/roster/Comet")
+            .body(reindeer_json("Comet", 9, 50.4))
+            .dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+
+        let response = client.get("/4/roster/strength").dispatch();
+        assert_eq!(response.into_string().unwrap(), "9");
+
+        let response = client.delete("/4/roster/Dasher").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+
+        let response = client.delete("/4/roster/Dasher").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+
+        let response = client.get("/4/roster").dispatch();
+        assert_eq!(response.into_string().unwrap(), "[]");
+    }
+
+    #[test]
+    fn test_roster_reserved_names() {
+        let client = client();
+
+        for name in RESERVED_NAMES {
+            let response = client
+                .post("/4/roster")
+                .body(reindeer_json(name, 5, 50.4))
+                .dispatch();
+            assert_eq!(response.status(), Status::BadRequest);
+
+            let response = client
+                .put(format!("/4/roster/{name}"))
+                .body(reindeer_json(name, 5, 50.4))
+                .dispatch();
+            assert_eq!(response.status(), Status::BadRequest);
+        }
+
+        // Other names that merely contain them are fine
+        let response = client
+            .post("/4/roster")
+            .body(reindeer_json("strengthy", 5, 50.4))
+            .dispatch();
+        assert_eq!(response.status(), Status::Created);
+        let response = client.get("/4/roster/strength").dispatch();
+        assert_eq!(response.into_string().unwrap(), "5");
+    }
+
+    #[test]
+    fn test_roster_contest() {
+        let client = client();
+
+        let response = client.get("/4/roster/contest").dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        for (name, speed) in [("Dasher", 50.4), ("Dancer", 60.1)] {
+            client
+                .post("/4/roster")
+                .body(reindeer_json(name, 5, speed))
+                .dispatch();
+        }
+
+        let response = client.get("/4/roster/contest").dispatch();
+        let winners: HashMap<String, String> = response.into_json().unwrap();
+        assert_eq!(
+            winners.get("fastest").unwrap(),
+            "Speeding past the finish line with a strength of 5 is Dancer"
+        );
+    }
+
+    #[test]
+    fn test_team_route() {
+        let client = client();
+        let herd = [
+            reindeer_json("Dasher", 5, 50.4),
+            reindeer_json("Dancer", 8, 50.4),
+            reindeer_json("Prancer", 3, 50.4),
+        ]
+        .join(",");
+
+        let body = format!(r#"{{"reindeers":[{herd}],"team_size":2,"max_candies":4}}"#);
+        let response = client.post("/4/team").body(body).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(
+            response.into_string().unwrap(),
+            r#"{"team":["Dasher","Dancer"],"strength":13,"candies":4,"snow_magic":18002,"height_spread":0,"optimal":true}"#
+        );
+
+        let body = format!(r#"{{"reindeers":[{herd}],"team_size":2,"max_candies":3}}"#);
+        let response = client.post("/4/team").body(body).dispatch();
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+    }
+
+    #[test]
+    fn test_stats_route() {
+        let client = client();
+        let herd = format!(
+            "[{},{},{}]",
+            reindeer_json("Dasher", 5, 50.0),
+            reindeer_json("Dancer", 8, 60.0),
+            reindeer_json("Prancer", 2, 40.0)
+        );
+
+        let response = client
+            .post("/4/stats?percentiles=50&buckets=2")
+            .body(&herd)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let stats: rocket::serde::json::Value = response.into_json().unwrap();
+        assert_eq!(stats["speed"]["mean"], 50.0);
+        assert_eq!(stats["speed"]["percentiles"][0]["value"], 50.0);
+        assert_eq!(stats["strength"]["max"], 8.0);
+        assert_eq!(stats["height"]["std_dev"], 0.0);
+        assert_eq!(stats["candies_eaten_yesterday"]["histogram"][0]["count"], 3);
+
+        let response = client
+            .post("/4/stats?percentiles=50,101")
+            .body(&herd)
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let response = client.post("/4/stats").body("[]").dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn test_strength_csv_and_ndjson() {
+        let client = client();
+
+        let csv = "name,strength\r\nDasher,5\r\n\"Rudolph, the red\",7\r\n\r\n";
+        let response = client
+            .post("/4/strength")
+            .header(ContentType::CSV)
+            .body(csv)
+            .dispatch();
+        assert_eq!(response.into_string().unwrap(), "12");
+
+        let ndjson =
+            "{\"name\":\"Dasher\",\"strength\":5}\n\n{\"name\":\"Dancer\",\"strength\":6}\n";
+        let response = client
+            .post("/4/strength")
+            .header(ContentType::new("application", "x-ndjson"))
+            .body(ndjson)
+            .dispatch();
+        assert_eq!(response.into_string().unwrap(), "11");
+
+        let response = client
+            .post("/4/strength")
+            .header(ContentType::CSV)
+            .body("name,strength\nDasher,lots\n")
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        // JSON is still the default
+        let response = client
+            .post("/4/strength")
+            .body(r#"[{"name":"Dasher","strength":5}]"#)
+            .dispatch();
+        assert_eq!(response.into_string().unwrap(), "5");
+    }
+
+    #[test]
+    fn test_crlf_csv_cut_at_limit() {
+        let client = |limit: u64| {
+            let dir = std::env::temp_dir().join(format!("day4-roster-{}", ulid::Ulid::new()));
+            let state = Day4State::new(PersistInstance::new(dir).unwrap());
+            let figment = rocket::Config::figment().merge(("limits.herd", limit));
+            let rocket = rocket::custom(figment).mount("/4", routes()).manage(state);
+            Client::tracked(rocket).expect("valid rocket instance")
+        };
+        // 36 bytes, so a 33 byte limit cuts the last row to "Dancer,1"
+        let csv = "name,strength\r\nDasher,5\r\nDancer,12\r\n";
+
+        let limited = client(64);
+        let response = limited
+            .post("/4/strength")
+            .header(ContentType::CSV)
+            .body(csv)
+            .dispatch();
+        assert_eq!(response.into_string().unwrap(), "17");
+
+        let limited = client(33);
+        let response = limited
+            .post("/4/strength")
+            .header(ContentType::CSV)
+            .body(csv)
+            .dispatch();
+        assert_eq!(response.status(), Status::PayloadTooLarge);
+    }
+
+    #[test]
+    fn test_contest_csv() {
+        let client = client();
+        let csv = "\
+name,strength,speed,height,antler_width,snow_magic_power,favorite_food,cAnD13s_3ATeN-yesT3rdAy
+Dasher,5,50.4,80,36,9001,hay,2
+Dancer,6,48.2,65,37,4004,\"grass,
+and hay\",5
+";
+        let response = client
+            .post("/4/contest")
+            .header(ContentType::CSV)
+            .body(csv)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let winners: HashMap<String, String> = response.into_json().unwrap();
+        assert_eq!(
+            winners.get("consumer").unwrap(),
+            "Dancer ate lots of candies, but also some grass,\nand hay"
+        );
+
+        let response = client
+            .post("/4/contest")
+            .header(ContentType::CSV)
+            .body("name,strength\nDasher,5\n")
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    fn racer(name: &str, strength: usize, speed: f32, candies: usize) -> FullReindeer {
+        let mut reindeer = create_full_reindeer(name, speed, 100, 30, 100, "hay", candies);
+        reindeer.base.strength = strength;
+        reindeer
+    }
+
+    #[test]
+    fn test_race_deterministic() {
+        let reindeers = vec![
+            racer("Dasher", 80, 10.0, 0),
+            racer("Dancer", 20, 11.0, 15),
+            racer("Prancer", 50, 9.0, 2),
+        ];
+
+        let first = race::simulate(&reindeers, 300.0, 42, 1000, 1);
+        let second = race::simulate(&reindeers, 300.0, 42, 1000, 1);
+        assert_eq!(first, second);
+
+        let order: Vec<&str> = first
+            .finishing_order
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(order, vec!["Dasher", "Prancer", "Dancer"]);
+        let times: Vec<String> = first
+            .finishing_order
+            .iter()
+            .map(|f| format!("{:.3}", f.time.unwrap()))
+            .collect();
+        assert_eq!(times, vec!["33.330", "39.153", "53.298"]);
+        assert_eq!(first.timeline.len(), 54);
+        assert_eq!(first.timeline[0].positions.len(), 3);
+    }
+
+    #[test]
+    fn test_race_out_of_ticks() {
+        let reindeers = vec![racer("Dasher", 80, 10.0, 0), racer("Comet", 80, 1.0, 0)];
+        let result = race::simulate(&reindeers, 50.0, 7, 10, 1);
+
+        assert_eq!(result.timeline.len(), 10);
+        assert!(result.finishing_order[0].time.is_some());
+        assert_eq!(result.finishing_order[1].name, "Comet");
+        assert_eq!(result.finishing_order[1].time, None);
+    }
+
+    #[test]
+    fn test_race_sampled_timeline() {
+        let reindeers = vec![racer("Dasher", 80, 10.0, 0), racer("Comet", 80, 1.0, 0)];
+        let every = race::simulate(&reindeers, 50.0, 7, 10, 1);
+        let sampled = race::simulate(&reindeers, 50.0, 7, 10, 4);
+
+        let ticks: Vec<usize> = sampled.timeline.iter().map(|t| t.tick).collect();
+        assert_eq!(ticks, vec![4, 8, 10]);
+        assert_eq!(sampled.timeline[1], every.timeline[7]);
+        assert_eq!(sampled.finishing_order, every.finishing_order);
+    }
+
+    #[test]
+    fn test_race_route() {
+        let client = client();
+        let herd = format!(
+            "[{},{}]",
+            reindeer_json("Dasher", 5, 50.4),
+            reindeer_json("Dancer", 8, 60.0)
+        );
+
+        let body = format!(r#"{{"reindeers":{herd},"distance":500,"seed":1}}"#);
+        let response = client.post("/4/race").body(&body).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let result: rocket::serde::json::Value = response.into_json().unwrap();
+        assert_eq!(result["finishing_order"][0]["name"], "Dancer");
+
+        let body = format!(r#"{{"reindeers":{herd},"distance":-1}}"#);
+        let response = client.post("/4/race").body(&body).dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let body = format!(r#"{{"reindeers":{herd},"distance":500,"max_ticks":1000000}}"#);
+        let response = client.post("/4/race").body(&body).dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let body = format!(
+            r#"{{"reindeers":{herd},"distance":500,"max_ticks":1000000,"sample_every":1000}}"#
+        );
+        let response = client.post("/4/race").body(&body).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    #[test]
+    fn test_unreadable_contest_log_is_not_overwritten() {
+        let dir = std::env::temp_dir().join(format!("day4-roster-{}", ulid::Ulid::new()));
+        let persist = PersistInstance::new(dir).unwrap();
+        persist.save(CONTEST_LOG_KEY, 7u8).unwrap();
+        let state = Day4State::new(persist.clone());
+        let rocket = rocket::build().mount("/4", routes()).manage(state);
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+
+        let response = client.get("/4/ratings").dispatch();
+        assert_eq!(response.status(), Status::InternalServerError);
+        let herd = format!("[{}]", reindeer_json("Dasher", 5, 50.4));
+        let response = client.post("/4/contest").body(herd).dispatch();
+        assert_eq!(response.status(), Status::InternalServerError);
+        assert_eq!(persist.load::<u8>(CONTEST_LOG_KEY).unwrap(), 7);
+    }
+
+    #[test]
+    fn test_day12_save_does_not_touch_contests() {
+        let client = client_with_day12();
+
+        let herd = format!("[{}]", reindeer_json("Dasher", 5, 50.4));
+        let response = client.post("/4/contest").body(herd).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        for packet_id in [
+            CONTEST_LOG_KEY,
+            contest_record_key(1).as_str(),
+            "contest_log",
+            "contest_1",
+        ] {
+            let response = client.post(format!("/12/save/{packet_id}")).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+        }
+
+        let response = client.get("/4/history").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let contests: rocket::serde::json::Value = response.into_json().unwrap();
+        assert_eq!(contests.as_array().unwrap().len(), 1);
+        assert_eq!(contests[0]["winners"]["fastest"], "Dasher");
+    }
+
+    #[test]
+    fn test_contest_ratings_and_history() {
+        let client = client();
+
+        let response = client.get("/4/ratings").dispatch();
+        assert_eq!(response.into_string().unwrap(), "[]");
+
+        let herd = format!(
+            "[{},{}]",
+            reindeer_json("Dasher", 5, 50.4),
+            reindeer_json("Dancer", 8, 60.0)
+        );
+        for _ in 0..2 {
+            let response = client.post("/4/contest").body(&herd).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+        }
+        let response = client.post("/4/contest").body("[]").dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let response = client.get("/4/ratings").dispatch();
+        let ratings: rocket::serde::json::Value = response.into_json().unwrap();
+        assert_eq!(ratings.as_array().unwrap().len(), 2);
+        assert_eq!(ratings[0]["name"], "Dancer");
+        assert_eq!(ratings[0]["contests"], 2);
+        assert_eq!(ratings[0]["wins"], 8);
+        assert!(ratings[0]["rating"].as_f64().unwrap() > elo::INITIAL_RATING);
+
+        let response = client.get("/4/history?name=Dasher").dispatch();
+        let history: Vec<HistoryPoint> = response.into_json().unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].contest, 1);
+        assert!(history[1].rating < history[0].rating);
+
+        let response = client.get("/4/history").dispatch();
+        let contests: rocket::serde::json::Value = response.into_json().unwrap();
+        assert_eq!(contests.as_array().unwrap().len(), 2);
+        assert_eq!(contests[0]["winners"]["fastest"], "Dancer");
+
+        let response = client.get("/4/history?name=Comet").dispatch();
+        assert_eq!(response.status(), Status::NotFound);
+
+        let response = client.get("/4/history?after=1").dispatch();
+        let contests: rocket::serde::json::Value = response.into_json().unwrap();
+        assert_eq!(contests.as_array().unwrap().len(), 1);
+        assert_eq!(contests[0]["id"], 2);
+
+        let response = client.get("/4/history?name=Dasher&limit=1").dispatch();
+        let history: Vec<HistoryPoint> = response.into_json().unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].contest, 1);
+
+        let response = client.get("/4/history?limit=0").dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
 }
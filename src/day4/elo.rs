@@ -0,0 +1,198 @@
+use rocket::serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+pub const INITIAL_RATING: f64 = 1500.0;
+// Most a reindeer can gain or lose in a single category
+const K_FACTOR: f64 = 32.0;
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Rating {
+    pub rating: f64,
+    pub contests: usize,
+    pub wins: usize,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Rating {
+            rating: INITIAL_RATING,
+            contests: 0,
+            wins: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ContestRecord {
+    // Set by ContestLog::record, counting from 1
+    pub id: usize,
+    pub timestamp: String,
+    pub participants: Vec<String>,
+    // Category name to winner name
+    pub winners: BTreeMap<String, String>,
+    // Each participant's rating once this contest was counted
+    pub ratings: BTreeMap<String, f64>,
+}
+
+impl ContestRecord {
+    pub fn new(
+        participants: &[String],
+        winners: &HashMap<String, String>,
+        timestamp: String,
+    ) -> Self {
+        let mut participants: Vec<String> = participants.to_vec();
+        participants.sort();
+        participants.dedup();
+
+        ContestRecord {
+            id: 0,
+            timestamp,
+            participants,
+            winners: winners.clone().into_iter().collect(),
+            ratings: BTreeMap::new(),
+        }
+    }
+}
+
+// Current ratings only; the records themselves are stored one by one
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct ContestLog {
+    pub ratings: BTreeMap<String, Rating>,
+    // Number of contests recorded, which is also the id of the latest one
+    pub contests: usize,
+}
+
+// Chance that a reindeer rated `a` beats one rated `b`
+fn expected_score(a: f64, b: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((b - a) / 400.0))
+}
+
+impl ContestLog {
+    // Each category counts as the winner beating every other participant, with K split across them.
+    // Gives the contest its id and fills in the participants' new ratings.
+    pub fn record(&mut self, contest: &mut ContestRecord) {
+        for name in &contest.participants {
+            self.ratings.entry(name.clone()).or_default().contests += 1;
+        }
+
+        for winner in contest.winners.values() {
+            let rating = |name: &String| self.ratings[name].rating;
+            let winner_rating = rating(winner);
+            let losers: Vec<(String, f64)> = contest
+                .participants
+                .iter()
+                .filter(|&name| name != winner)
+                .map(|name| (name.clone(), rating(name)))
+                .collect();
+            if losers.is_empty() {
+                continue;
+            }
+
+            let k = K_FACTOR / losers.len() as f64;
+            let mut gained = 0.0;
+            for (loser, loser_rating) in losers {
+                let delta = k * (1.0 - expected_score(winner_rating, loser_rating));
+                gained += delta;
+                self.ratings.get_mut(&loser).unwrap().rating -= delta;
+            }
+            let entry = self.ratings.get_mut(winner).unwrap();
+            entry.rating += gained;
+            entry.wins += 1;
+        }
+
+        self.contests += 1;
+        contest.id = self.contests;
+        contest.ratings = contest
+            .participants
+            .iter()
+            .map(|name| (name.clone(), self.ratings[name].rating))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|n| n.to_string()).collect()
+    }
+
+    fn winners(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(c, n)| (c.to_string(), n.to_string()))
+            .collect()
+    }
+
+    fn record(
+        log: &mut ContestLog,
+        participants: &[String],
+        winners: &HashMap<String, String>,
+    ) -> ContestRecord {
+        let mut contest = ContestRecord::new(participants, winners, "t".to_string());
+        log.record(&mut contest);
+        contest
+    }
+
+    #[test]
+    fn test_expected_score() {
+        assert_eq!(expected_score(1500.0, 1500.0), 0.5);
+        assert!((expected_score(1900.0, 1500.0) - 0.909).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_record_two_reindeer() {
+        let mut log = ContestLog::default();
+        let participants = names(&["Dasher", "Dancer"]);
+        record(&mut log, &participants, &winners(&[("fastest", "Dancer")]));
+
+        assert_eq!(log.ratings["Dancer"].rating, 1516.0);
+        assert_eq!(log.ratings["Dasher"].rating, 1484.0);
+        assert_eq!(log.ratings["Dancer"].wins, 1);
+        assert_eq!(log.ratings["Dasher"].contests, 1);
+
+        // The favourite gains less for winning again
+        let second = record(&mut log, &participants, &winners(&[("fastest", "Dancer")]));
+        let gain = log.ratings["Dancer"].rating - 1516.0;
+        assert!(gain > 0.0 && gain < 16.0);
+        assert_eq!(log.contests, 2);
+        assert_eq!(second.id, 2);
+        assert_eq!(second.ratings["Dancer"], log.ratings["Dancer"].rating);
+    }
+
+    #[test]
+    fn test_record_conserves_rating() {
+        let mut log = ContestLog::default();
+        let participants = names(&["Dasher", "Dancer", "Prancer", "Dasher"]);
+        let contest = record(
+            &mut log,
+            &participants,
+            &winners(&[("fastest", "Dancer"), ("tallest", "Prancer")]),
+        );
+
+        assert_eq!(
+            contest.participants,
+            names(&["Dancer", "Dasher", "Prancer"])
+        );
+        let total: f64 = log.ratings.values().map(|r| r.rating).sum();
+        assert!((total - 3.0 * INITIAL_RATING).abs() < 1e-9);
+        assert!(log.ratings["Dasher"].rating < INITIAL_RATING);
+    }
+
+    #[test]
+    fn test_record_single_reindeer() {
+        let mut log = ContestLog::default();
+        record(
+            &mut log,
+            &names(&["Dasher"]),
+            &winners(&[("fastest", "Dasher")]),
+        );
+
+        assert_eq!(log.ratings["Dasher"].rating, INITIAL_RATING);
+        assert_eq!(log.ratings["Dasher"].wins, 0);
+    }
+}
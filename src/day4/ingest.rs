@@ -0,0 +1,275 @@
+use super::{FullReindeer, Reindeer};
+use rocket::data::{self, ByteUnit, Data, DataStream, FromData};
+use rocket::http::Status;
+use rocket::serde::json::serde_json;
+use rocket::serde::DeserializeOwned;
+use rocket::Request;
+use std::str::FromStr;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+// Default cap on a streamed herd body, override with the `herd` limit
+const HERD_LIMIT: ByteUnit = ByteUnit::Mebibyte(64);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum RowFormat {
+    Csv,
+    Ndjson,
+}
+
+// A CSV (text/csv) or NDJSON (application/x-ndjson) body read one row at a time;
+// any other content type forwards to the JSON route
+pub struct RowStream<'r> {
+    format: RowFormat,
+    reader: BufReader<DataStream<'r>>,
+    limit: u64,
+    bytes_read: u64,
+    line_number: usize,
+    headers: Option<Vec<String>>,
+}
+
+#[rocket::async_trait]
+impl<'r> FromData<'r> for RowStream<'r> {
+    type Error = String;
+
+    async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        let format = match req.content_type() {
+            Some(ct) if ct.is_csv() => RowFormat::Csv,
+            Some(ct) if ct.top() == "application" && ct.sub() == "x-ndjson" => RowFormat::Ndjson,
+            _ => return data::Outcome::Forward((data, Status::UnsupportedMediaType)),
+        };
+
+        let limit = req.limits().get("herd").unwrap_or(HERD_LIMIT);
+        data::Outcome::Success(RowStream {
+            format,
+            reader: BufReader::new(data.open(limit)),
+            limit: limit.as_u64(),
+            bytes_read: 0,
+            line_number: 0,
+            headers: None,
+        })
+    }
+}
+
+pub trait FromCsvRow: Sized {
+    fn from_csv_row(row: &CsvRow) -> Result<Self, String>;
+}
+
+pub struct CsvRow<'a> {
+    headers: &'a [String],
+    values: Vec<String>,
+}
+
+impl<'a> CsvRow<'a> {
+    fn get(&self, column: &str) -> Result<&str, String> {
+        self.headers
+            .iter()
+            .position(|h| h == column)
+            .and_then(|i| self.values.get(i))
+            .map(|v| v.as_str())
+            .ok_or_else(|| format!("Missing column {column}"))
+    }
+
+    fn parse<T: FromStr>(&self, column: &str) -> Result<T, String> {
+        let value = self.get(column)?;
+        value
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid {column} {value}"))
+    }
+}
+
+impl FromCsvRow for Reindeer {
+    fn from_csv_row(row: &CsvRow) -> Result<Self, String> {
+        Ok(Reindeer {
+            name: row.get("name")?.to_string(),
+            strength: row.parse("strength")?,
+        })
+    }
+}
+
+impl FromCsvRow for FullReindeer {
+    fn from_csv_row(row: &CsvRow) -> Result<Self, String> {
+        Ok(FullReindeer {
+            base: Reindeer::from_csv_row(row)?,
+            speed: row.parse("speed")?,
+            height: row.parse("height")?,
+            antler_width: row.parse("antler_width")?,
+            snow_magic_power: row.parse("snow_magic_power")?,
+            favorite_food: row.get("favorite_food")?.to_string(),
+            candies_eaten_yesterday: row.parse("candies_eaten_yesterday")?,
+        })
+    }
+}
+
+// The stables export the candy column under its JSON name, so accept both spellings
+fn normalize_header(header: &str) -> String {
+    let header = header.trim();
+    if header.eq_ignore_ascii_case("cAnD13s_3ATeN-yesT3rdAy") {
+        return "candies_eaten_yesterday".to_string();
+    }
+    header.to_ascii_lowercase()
+}
+
+// Splits one CSV record, handling quoted fields with "" escapes; None if a quote is left open
+fn parse_csv_record(record: &str) -> Option<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = record.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if field.is_empty() => in_quotes = true,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            _ => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return None;
+    }
+    fields.push(field);
+    Some(fields)
+}
+
+impl<'r> RowStream<'r> {
+    async fn next_line(&mut self) -> Result<Option<String>, Status> {
+        let mut buf = Vec::new();
+        let read = self.reader.read_until(b'\n', &mut buf).await.map_err(|e| {
+            println!("Failed to read herd body: {e}");
+            Status::BadRequest
+        })?;
+        if read == 0 {
+            return Ok(None);
+        }
+
+        self.line_number += 1;
+        // Count the raw bytes, line endings included, so CRLF bodies hit the limit too
+        self.bytes_read += read as u64;
+        // The stream silently stops at the limit, so a body that reaches it was truncated
+        if self.bytes_read >= self.limit {
+            println!("Herd body exceeds {} bytes", self.limit);
+            return Err(Status::PayloadTooLarge);
+        }
+
+        if buf.ends_with(b"\n") {
+            buf.pop();
+            if buf.ends_with(b"\r") {
+                buf.pop();
+            }
+        }
+        String::from_utf8(buf).map(Some).map_err(|e| {
+            println!("Herd body line {} is not UTF-8: {e}", self.line_number);
+            Status::BadRequest
+        })
+    }
+
+    // Reads lines until they form a complete CSV record, so quoted fields may span lines
+    async fn next_csv_record(&mut self) -> Result<Option<Vec<String>>, Status> {
+        let mut record = match self.next_line().await? {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+
+        loop {
+            if let Some(fields) = parse_csv_record(record.trim_end_matches('\r')) {
+                return Ok(Some(fields));
+            }
+            match self.next_line().await? {
+                Some(line) => {
+                    record.push('\n');
+                    record.push_str(&line);
+                }
+                None => {
+                    println!(
+                        "Unterminated quote in CSV record ending on line {}",
+                        self.line_number
+                    );
+                    return Err(Status::BadRequest);
+                }
+            }
+        }
+    }
+
+    // The next row, skipping blank lines; the first CSV record is the header
+    pub async fn next<T: FromCsvRow + DeserializeOwned>(&mut self) -> Result<Option<T>, Status> {
+        loop {
+            let row = match self.format {
+                RowFormat::Ndjson => match self.next_line().await? {
+                    Some(line) if line.trim().is_empty() => continue,
+                    Some(line) => serde_json::from_str::<T>(&line).map_err(|e| e.to_string()),
+                    None => return Ok(None),
+                },
+                RowFormat::Csv => match self.next_csv_record().await? {
+                    Some(fields) if fields.iter().all(|f| f.trim().is_empty()) => continue,
+                    Some(fields) => match &self.headers {
+                        Some(headers) => T::from_csv_row(&CsvRow {
+                            headers,
+                            values: fields,
+                        }),
+                        None => {
+                            self.headers =
+                                Some(fields.iter().map(|h| normalize_header(h)).collect());
+                            continue;
+                        }
+                    },
+                    None => return Ok(None),
+                },
+            };
+
+            return row.map(Some).map_err(|e| {
+                println!("Invalid herd row on line {}: {e}", self.line_number);
+                Status::BadRequest
+            });
+        }
+    }
+
+    pub async fn collect<T: FromCsvRow + DeserializeOwned>(mut self) -> Result<Vec<T>, Status> {
+        let mut rows = Vec::new();
+        while let Some(row) = self.next().await? {
+            rows.push(row);
+        }
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv_record() {
+        assert_eq!(
+            parse_csv_record("Dasher,5,hay"),
+            Some(vec![
+                "Dasher".to_string(),
+                "5".to_string(),
+                "hay".to_string()
+            ])
+        );
+        assert_eq!(
+            parse_csv_record(r#""Rudolph, the red",3,"say ""hi""""#),
+            Some(vec![
+                "Rudolph, the red".to_string(),
+                "3".to_string(),
+                r#"say "hi""#.to_string()
+            ])
+        );
+        assert_eq!(parse_csv_record(r#""open,1"#), None);
+        assert_eq!(parse_csv_record(""), Some(vec!["".to_string()]));
+    }
+
+    #[test]
+    fn test_normalize_header() {
+        assert_eq!(
+            normalize_header(" cAnD13s_3ATeN-yesT3rdAy "),
+            "candies_eaten_yesterday"
+        );
+        assert_eq!(normalize_header("Name"), "name");
+    }
+}
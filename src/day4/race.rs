@@ -0,0 +1,125 @@
+use super::FullReindeer;
+use rocket::serde::Serialize;
+
+// Share of its speed a reindeer loses per tick with no strength and no candies in it
+const BASE_FATIGUE: f64 = 0.01;
+// Worn out reindeer still trot along at this share of their speed
+const MIN_EFFICIENCY: f64 = 0.25;
+// Each tick's speed varies by up to this share either way
+const JITTER: f64 = 0.1;
+
+// SplitMix64, so a seed gives the same race everywhere
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Finisher {
+    pub name: String,
+    // Fractional tick the finish line was crossed, None if the race ran out of ticks first
+    pub time: Option<f64>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Tick {
+    pub tick: usize,
+    // Distance covered by each reindeer, in herd order
+    pub positions: Vec<f64>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RaceResult {
+    pub finishing_order: Vec<Finisher>,
+    pub timeline: Vec<Tick>,
+}
+
+// Strength makes a reindeer tire slower, yesterday's candies make it tire faster
+fn fatigue_per_tick(reindeer: &FullReindeer) -> f64 {
+    let stamina = 1.0 + reindeer.base.strength as f64 / 100.0;
+    let sugar_crash = 1.0 + reindeer.candies_eaten_yesterday as f64 / 10.0;
+    BASE_FATIGUE * sugar_crash / stamina
+}
+
+pub fn simulate(
+    reindeers: &[FullReindeer],
+    distance: f64,
+    seed: u64,
+    max_ticks: usize,
+    sample_every: usize,
+) -> RaceResult {
+    let mut rng = Rng(seed);
+    let mut positions = vec![0.0f64; reindeers.len()];
+    let mut fatigue = vec![0.0f64; reindeers.len()];
+    let mut finish_times: Vec<Option<f64>> = vec![None; reindeers.len()];
+    let mut timeline = Vec::new();
+
+    for tick in 1..=max_ticks {
+        if finish_times.iter().all(|t| t.is_some()) {
+            break;
+        }
+
+        for (i, reindeer) in reindeers.iter().enumerate() {
+            // Draw for everyone every tick so one reindeer finishing doesn't change the others' luck
+            let jitter = 1.0 + JITTER * (2.0 * rng.next_f64() - 1.0);
+            if finish_times[i].is_some() {
+                continue;
+            }
+
+            let efficiency = (1.0 - fatigue[i]).max(MIN_EFFICIENCY);
+            let step = reindeer.speed as f64 * efficiency * jitter;
+            fatigue[i] += fatigue_per_tick(reindeer);
+
+            if step > 0.0 && positions[i] + step >= distance {
+                finish_times[i] = Some((tick - 1) as f64 + (distance - positions[i]) / step);
+                positions[i] = distance;
+            } else {
+                positions[i] += step.max(0.0);
+            }
+        }
+
+        // Every sample_every ticks, plus the tick the race ended on
+        let finished = finish_times.iter().all(|t| t.is_some());
+        if tick % sample_every == 0 || finished || tick == max_ticks {
+            timeline.push(Tick {
+                tick,
+                positions: positions.clone(),
+            });
+        }
+    }
+
+    // Finishers by time, then everyone else by distance covered; ties keep herd order
+    let mut order: Vec<usize> = (0..reindeers.len()).collect();
+    order.sort_by(|&a, &b| match (finish_times[a], finish_times[b]) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => positions[b].total_cmp(&positions[a]),
+    });
+
+    RaceResult {
+        finishing_order: order
+            .into_iter()
+            .map(|i| Finisher {
+                name: reindeers[i].base.name.clone(),
+                time: finish_times[i],
+            })
+            .collect(),
+        timeline,
+    }
+}
@@ -0,0 +1,160 @@
+use rocket::serde::Serialize;
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Percentile {
+    pub percentile: f64,
+    pub value: f64,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Bucket {
+    pub start: f64,
+    pub end: f64,
+    pub count: usize,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Summary {
+    pub count: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    pub percentiles: Vec<Percentile>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub histogram: Option<Vec<Bucket>>,
+}
+
+// Linear interpolation between closest ranks, like numpy's default
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p / 100.0 * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+// Equal-width buckets from min to max; the last bucket includes max
+fn histogram(sorted: &[f64], buckets: usize) -> Vec<Bucket> {
+    let min = sorted[0];
+    let max = sorted[sorted.len() - 1];
+    let width = (max - min) / buckets as f64;
+
+    let mut result: Vec<Bucket> = (0..buckets)
+        .map(|i| Bucket {
+            start: min + width * i as f64,
+            end: min + width * (i + 1) as f64,
+            count: 0,
+        })
+        .collect();
+    for value in sorted {
+        let index = match width > 0.0 {
+            true => (((value - min) / width) as usize).min(buckets - 1),
+            false => 0,
+        };
+        result[index].count += 1;
+    }
+
+    result
+}
+
+// Values must be finite; None for an empty sample
+pub fn describe(values: &[f64], percentiles: &[f64], buckets: Option<usize>) -> Option<Summary> {
+    if values.is_empty() {
+        return None;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+
+    let count = sorted.len();
+    let mean = sorted.iter().sum::<f64>() / count as f64;
+    let variance = sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count as f64;
+
+    Some(Summary {
+        count,
+        mean,
+        median: percentile(&sorted, 50.0),
+        std_dev: variance.sqrt(),
+        min: sorted[0],
+        max: sorted[count - 1],
+        percentiles: percentiles
+            .iter()
+            .map(|&p| Percentile {
+                percentile: p,
+                value: percentile(&sorted, p),
+            })
+            .collect(),
+        histogram: buckets.map(|b| histogram(&sorted, b)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        let summary = describe(
+            &[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0],
+            &[25.0, 90.0],
+            None,
+        )
+        .unwrap();
+        assert_eq!(summary.count, 8);
+        assert_eq!(summary.mean, 5.0);
+        assert_eq!(summary.median, 4.5);
+        assert_eq!(summary.std_dev, 2.0);
+        assert_eq!(summary.min, 2.0);
+        assert_eq!(summary.max, 9.0);
+        assert_eq!(
+            summary.percentiles,
+            vec![
+                Percentile {
+                    percentile: 25.0,
+                    value: 4.0
+                },
+                Percentile {
+                    percentile: 90.0,
+                    value: 7.6
+                },
+            ]
+        );
+        assert_eq!(summary.histogram, None);
+    }
+
+    #[test]
+    fn test_describe_empty() {
+        assert_eq!(describe(&[], &[50.0], Some(3)), None);
+    }
+
+    #[test]
+    fn test_histogram() {
+        let summary = describe(&[0.0, 1.0, 2.0, 5.0, 9.0, 10.0], &[], Some(2)).unwrap();
+        assert_eq!(
+            summary.histogram.unwrap(),
+            vec![
+                Bucket {
+                    start: 0.0,
+                    end: 5.0,
+                    count: 3
+                },
+                Bucket {
+                    start: 5.0,
+                    end: 10.0,
+                    count: 3
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_histogram_single_value() {
+        let summary = describe(&[3.0, 3.0], &[], Some(4)).unwrap();
+        let counts: Vec<usize> = summary.histogram.unwrap().iter().map(|b| b.count).collect();
+        assert_eq!(counts, vec![2, 0, 0, 0]);
+    }
+}
@@ -0,0 +1,654 @@
+use super::FullReindeer;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Constraints {
+    pub team_size: usize,
+    pub max_candies: Option<usize>,
+    pub min_snow_magic: Option<usize>,
+    pub max_height_spread: Option<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Team {
+    // Indices into the herd, in herd order
+    pub members: Vec<usize>,
+    // Totals are u128 so a team of huge reindeer can't overflow them
+    pub strength: u128,
+    pub candies: usize,
+    pub snow_magic: u128,
+    // False if the node limit was hit before the team was proven best
+    pub optimal: bool,
+}
+
+#[derive(Clone, Copy)]
+struct Candidate {
+    index: usize,
+    strength: u128,
+    candies: usize,
+    magic: u128,
+}
+
+// Upper limit on (candidates × team size × units) for each knapsack table
+const MAX_KNAPSACK_CELLS: usize = 500_000;
+// Branch and bound gives up on proving optimality after visiting this many nodes
+const MAX_NODES: usize = 1_000_000;
+
+// Sum of the `j` largest values among candidates[i..], stored at [i * (k + 1) + j]
+fn suffix_top_sums<T>(candidates: &[Candidate], k: usize, value: impl Fn(&Candidate) -> T) -> Vec<T>
+where
+    T: Copy + Default + PartialOrd + std::ops::Add<Output = T>,
+{
+    let mut sums = vec![T::default(); (candidates.len() + 1) * (k + 1)];
+    let mut top: Vec<T> = Vec::with_capacity(k + 1);
+
+    for (i, candidate) in candidates.iter().enumerate().rev() {
+        let v = value(candidate);
+        let pos = top.iter().position(|t| v > *t).unwrap_or(top.len());
+        top.insert(pos, v);
+        top.truncate(k);
+
+        let mut acc = T::default();
+        for j in 1..=k {
+            if let Some(t) = top.get(j - 1) {
+                acc = acc + *t;
+            }
+            sums[i * (k + 1) + j] = acc;
+        }
+    }
+
+    sums
+}
+
+// Best strength of j of candidates[i..] whose units total at most (or at least) u, -1 if
+// impossible, stored at [(i * (k + 1) + j) * (limit + 1) + u]. A limit too big for the table is
+// counted in coarser units, rounded so every real team still fits: an upper bound, not exact.
+struct Knapsack {
+    unit: u128,
+    limit: usize,
+    table: Vec<i64>,
+}
+
+impl Knapsack {
+    // None when even a table of one unit would be too big
+    fn new(
+        candidates: &[Candidate],
+        k: usize,
+        limit: u128,
+        value: impl Fn(&Candidate) -> u128,
+        at_least: bool,
+    ) -> Option<Knapsack> {
+        let n = candidates.len();
+        let rows = (n + 1) * (k + 1);
+        let max_units = (MAX_KNAPSACK_CELLS / rows)
+            .checked_sub(1)
+            .filter(|&u| u > 0)?;
+        let unit = limit.div_ceil(max_units as u128).max(1);
+        // Rounding the limit and each value the same way keeps every real team within it
+        let scale = |v: u128| match at_least {
+            true => v.div_ceil(unit),
+            false => v / unit,
+        };
+        let limit = scale(limit) as usize;
+        let units: Vec<usize> = candidates
+            .iter()
+            .map(|c| scale(value(c)).min(limit as u128 + 1) as usize)
+            .collect();
+
+        let cell = |i: usize, j: usize, u: usize| (i * (k + 1) + j) * (limit + 1) + u;
+        let mut table = vec![-1i64; rows * (limit + 1)];
+        for u in 0..=limit {
+            if !at_least || u == 0 {
+                table[cell(n, 0, u)] = 0;
+            }
+        }
+
+        for (i, candidate) in candidates.iter().enumerate().rev() {
+            for j in 0..=k {
+                for u in 0..=limit {
+                    let mut best = table[cell(i + 1, j, u)];
+                    let rest = match at_least {
+                        true => Some(u.saturating_sub(units[i])),
+                        false => u.checked_sub(units[i]),
+                    };
+                    if let (true, Some(rest)) = (j > 0, rest) {
+                        let rest = table[cell(i + 1, j - 1, rest)];
+                        if rest >= 0 {
+                            // Can't overflow, Bounds only builds tables when the total strength fits
+                            best = best.max(rest + candidate.strength as i64);
+                        }
+                    }
+                    table[cell(i, j, u)] = best;
+                }
+            }
+        }
+
+        Some(Knapsack { unit, limit, table })
+    }
+
+    // Best strength with `amount` more (or less) of the value to go, None if there's none
+    fn get(&self, at: usize, amount: u128, at_least: bool) -> Option<u128> {
+        let units = match at_least {
+            true => amount.div_ceil(self.unit),
+            false => amount / self.unit,
+        };
+        let u = units.min(self.limit as u128) as usize;
+        u128::try_from(self.table[at * (self.limit + 1) + u]).ok()
+    }
+}
+
+// Optimistic estimates of what the rest of a partial team can still achieve
+struct Bounds {
+    k: usize,
+    // Strength within the remaining candy budget, and strength with the missing magic
+    candy_knapsack: Option<Knapsack>,
+    magic_knapsack: Option<Knapsack>,
+    strength_top: Vec<u128>,
+    min_candies_suffix: Vec<usize>,
+    magic_top: Vec<u128>,
+    // Lagrangian relaxations of the magic and candy constraints: top sums of
+    // strength + λ·magic - μ·candies for a grid of (λ, μ)
+    lagrangian: Vec<(f64, f64, Vec<f64>)>,
+}
+
+impl Bounds {
+    fn new(candidates: &[Candidate], constraints: &Constraints) -> Self {
+        let n = candidates.len();
+        let k = constraints.team_size;
+
+        // The knapsack tables hold strengths as i64, so only build them when no team can exceed that
+        let total_strength: u128 = candidates.iter().map(|c| c.strength).sum();
+        let fits = total_strength <= i64::MAX as u128;
+        let candy_knapsack = constraints.max_candies.filter(|_| fits).and_then(|budget| {
+            Knapsack::new(candidates, k, budget as u128, |c| c.candies as u128, false)
+        });
+        let magic_knapsack = constraints
+            .min_snow_magic
+            .filter(|&min| fits && min > 0)
+            .and_then(|min| Knapsack::new(candidates, k, min as u128, |c| c.magic, true));
+
+        let mut min_candies_suffix = vec![usize::MAX; n + 1];
+        for (i, c) in candidates.iter().enumerate().rev() {
+            min_candies_suffix[i] = min_candies_suffix[i + 1].min(c.candies);
+        }
+
+        // Multipliers scaled so strength and the relaxed quantity are comparable
+        let strength: f64 = candidates.iter().map(|c| c.strength as f64).sum::<f64>() + 1.0;
+        let multipliers = |relaxed: Option<usize>, total: f64| -> Vec<f64> {
+            match relaxed {
+                Some(_) => std::iter::once(0.0)
+                    .chain((-4..=4).map(|exp| strength / total * 2f64.powi(exp)))
+                    .collect(),
+                None => vec![0.0],
+            }
+        };
+        let lambdas = multipliers(
+            constraints.min_snow_magic,
+            candidates.iter().map(|c| c.magic as f64).sum::<f64>() + 1.0,
+        );
+        let mus = multipliers(
+            constraints.max_candies,
+            candidates.iter().map(|c| c.candies as f64).sum::<f64>() + 1.0,
+        );
+
+        let mut lagrangian = Vec::new();
+        for &lambda in &lambdas {
+            for &mu in &mus {
+                if lambda == 0.0 && mu == 0.0 {
+                    continue;
+                }
+                let sums = suffix_top_sums(candidates, k, |c| {
+                    c.strength as f64 + lambda * c.magic as f64 - mu * c.candies as f64
+                });
+                lagrangian.push((lambda, mu, sums));
+            }
+        }
+
+        Bounds {
+            k,
+            candy_knapsack,
+            magic_knapsack,
+            strength_top: suffix_top_sums(candidates, k, |c| c.strength),
+            min_candies_suffix,
+            magic_top: suffix_top_sums(candidates, k, |c| c.magic),
+            lagrangian,
+        }
+    }
+
+    // Most strength `need` more of candidates[next..] could add, or None if no completion can be feasible
+    fn remaining_strength(
+        &self,
+        next: usize,
+        need: usize,
+        candies: usize,
+        magic: u128,
+        constraints: &Constraints,
+    ) -> Option<u128> {
+        let at = next * (self.k + 1) + need;
+
+        let mut bound = self.strength_top[at];
+        if let Some(max_candies) = constraints.max_candies {
+            let left = max_candies.checked_sub(candies)?;
+            if self.min_candies_suffix[next].saturating_mul(need) > left {
+                return None;
+            }
+            if let Some(knapsack) = &self.candy_knapsack {
+                bound = bound.min(knapsack.get(at, left as u128, false)?);
+            }
+        }
+
+        let deficit = (constraints.min_snow_magic.unwrap_or(0) as u128).saturating_sub(magic);
+        if self.magic_top[at] < deficit {
+            return None;
+        }
+        if let Some(knapsack) = &self.magic_knapsack {
+            bound = bound.min(knapsack.get(at, deficit, true)?);
+        }
+
+        let left = constraints
+            .max_candies
+            .map_or(0, |max| max.saturating_sub(candies));
+        for (lambda, mu, sums) in &self.lagrangian {
+            let relaxed = lambda * deficit as f64;
+            let refund = mu * left as f64;
+            // Slack relative to the terms so rounding never cuts off an exact integer optimum,
+            // even for strengths too big for an f64 to hold exactly
+            let slack = 1e-6 + 1e-9 * (sums[at].abs() + relaxed + refund);
+            let estimate = (sums[at] - relaxed + refund + slack).floor();
+            if estimate < 0.0 {
+                return None;
+            }
+            bound = bound.min(estimate as u128);
+        }
+
+        Some(bound)
+    }
+}
+
+struct Search<'a> {
+    candidates: &'a [Candidate],
+    bounds: Bounds,
+    constraints: Constraints,
+    chosen: Vec<usize>,
+    best: Option<Team>,
+    // Shared by every height window
+    nodes_left: usize,
+}
+
+impl<'a> Search<'a> {
+    fn new(
+        candidates: &'a [Candidate],
+        constraints: Constraints,
+        best: Option<Team>,
+        nodes_left: usize,
+    ) -> Self {
+        Search {
+            candidates,
+            bounds: Bounds::new(candidates, &constraints),
+            constraints,
+            chosen: Vec::with_capacity(constraints.team_size),
+            best,
+            nodes_left,
+        }
+    }
+
+    fn run(&mut self) {
+        self.branch(0, 0, 0, 0);
+    }
+
+    fn branch(&mut self, next: usize, strength: u128, candies: usize, magic: u128) {
+        if self.nodes_left == 0 {
+            return;
+        }
+        self.nodes_left -= 1;
+
+        let need = self.constraints.team_size - self.chosen.len();
+        if need == 0 {
+            let improves = match &self.best {
+                Some(best) => strength > best.strength,
+                None => true,
+            };
+            if improves && magic >= self.constraints.min_snow_magic.unwrap_or(0) as u128 {
+                let mut members: Vec<usize> = self
+                    .chosen
+                    .iter()
+                    .map(|&c| self.candidates[c].index)
+                    .collect();
+                members.sort();
+                self.best = Some(Team {
+                    members,
+                    strength,
+                    candies,
+                    snow_magic: magic,
+                    optimal: true,
+                });
+            }
+            return;
+        }
+        if self.candidates.len() - next < need {
+            return;
+        }
+
+        let remaining =
+            self.bounds
+                .remaining_strength(next, need, candies, magic, &self.constraints);
+        match (remaining, &self.best) {
+            (None, _) => return,
+            (Some(remaining), Some(best)) if strength + remaining <= best.strength => return,
+            _ => {}
+        }
+
+        let candidate = self.candidates[next];
+        // A total too big for a usize is over any budget, and couldn't be reported without one
+        let with_candidate = match (
+            candies.checked_add(candidate.candies),
+            self.constraints.max_candies,
+        ) {
+            (Some(total), Some(max)) if total > max => None,
+            (total, _) => total,
+        };
+        if let Some(with_candidate) = with_candidate {
+            self.chosen.push(next);
+            self.branch(
+                next + 1,
+                strength + candidate.strength,
+                with_candidate,
+                magic + candidate.magic,
+            );
+            self.chosen.pop();
+        }
+
+        self.branch(next + 1, strength, candies, magic);
+    }
+}
+
+// Branch-and-bound over every height window allowed by the spread; None when no team fits, or
+// none was found before the node limit
+pub fn best_team(reindeers: &[FullReindeer], constraints: Constraints) -> Option<Team> {
+    if constraints.team_size == 0 || constraints.team_size > reindeers.len() {
+        return None;
+    }
+
+    let mut by_height: Vec<usize> = (0..reindeers.len()).collect();
+    by_height.sort_by_key(|&i| reindeers[i].height);
+
+    let mut best: Option<Team> = None;
+    let mut nodes_left = MAX_NODES;
+    let mut previous_end = 0;
+    for start in 0..by_height.len() {
+        let min_height = reindeers[by_height[start]].height;
+        let end = match constraints.max_height_spread {
+            Some(spread) => {
+                by_height[start..]
+                    .iter()
+                    .take_while(|&&i| reindeers[i].height - min_height <= spread)
+                    .count()
+                    + start
+            }
+            None => by_height.len(),
+        };
+
+        // A window that ends where the previous one did is a subset of it
+        if end - start < constraints.team_size || (start > 0 && end == previous_end) {
+            previous_end = end;
+            continue;
+        }
+        previous_end = end;
+
+        let mut candidates: Vec<Candidate> = by_height[start..end]
+            .iter()
+            .map(|&index| Candidate {
+                index,
+                strength: reindeers[index].base.strength as u128,
+                candies: reindeers[index].candies_eaten_yesterday,
+                magic: reindeers[index].snow_magic_power as u128,
+            })
+            .collect();
+        candidates.sort_by(|a, b| b.strength.cmp(&a.strength).then(a.index.cmp(&b.index)));
+
+        let mut search = Search::new(&candidates, constraints, best.take(), nodes_left);
+        search.run();
+        best = search.best;
+        nodes_left = search.nodes_left;
+
+        if constraints.max_height_spread.is_none() || nodes_left == 0 {
+            break;
+        }
+    }
+
+    best.map(|team| Team {
+        optimal: nodes_left > 0,
+        ..team
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Reindeer;
+    use super::*;
+
+    fn reindeer(strength: usize, height: usize, magic: usize, candies: usize) -> FullReindeer {
+        FullReindeer {
+            base: Reindeer {
+                name: format!("R{strength}-{height}"),
+                strength,
+            },
+            speed: 0.0,
+            height,
+            antler_width: 0,
+            snow_magic_power: magic,
+            favorite_food: "hay".to_string(),
+            candies_eaten_yesterday: candies,
+        }
+    }
+
+    // Deterministic pseudo-random herd so failures are reproducible
+    fn herd(size: usize, seed: u64) -> Vec<FullReindeer> {
+        let mut state = seed;
+        let mut next = |modulo: u64| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            ((state >> 33) % modulo) as usize
+        };
+        (0..size)
+            .map(|_| reindeer(next(100), 100 + next(60), next(500), next(20)))
+            .collect()
+    }
+
+    // Like `herd`, but the strongest reindeer have the least magic
+    fn anticorrelated_herd(size: usize, seed: u64) -> Vec<FullReindeer> {
+        herd(size, seed)
+            .into_iter()
+            .map(|mut r| {
+                r.snow_magic_power = 1050 - r.base.strength * 10 + r.snow_magic_power % 100;
+                r.base.strength *= 10;
+                r
+            })
+            .collect()
+    }
+
+    fn brute_force(reindeers: &[FullReindeer], constraints: Constraints) -> Option<u128> {
+        let n = reindeers.len();
+        let mut best = None;
+        for mask in 0u32..(1 << n) {
+            if mask.count_ones() as usize != constraints.team_size {
+                continue;
+            }
+            let team: Vec<&FullReindeer> = (0..n)
+                .filter(|i| mask & (1 << i) != 0)
+                .map(|i| &reindeers[i])
+                .collect();
+            let candies: usize = team.iter().map(|r| r.candies_eaten_yesterday).sum();
+            let magic: usize = team.iter().map(|r| r.snow_magic_power).sum();
+            let spread = team.iter().map(|r| r.height).max().unwrap()
+                - team.iter().map(|r| r.height).min().unwrap();
+            if constraints.max_candies.is_some_and(|max| candies > max)
+                || constraints.min_snow_magic.is_some_and(|min| magic < min)
+                || constraints
+                    .max_height_spread
+                    .is_some_and(|max| spread > max)
+            {
+                continue;
+            }
+            let strength = team.iter().map(|r| r.base.strength as u128).sum();
+            best = best.max(Some(strength));
+        }
+        best
+    }
+
+    #[test]
+    fn test_unconstrained_picks_strongest() {
+        let reindeers = vec![
+            reindeer(10, 100, 0, 0),
+            reindeer(50, 100, 0, 0),
+            reindeer(30, 100, 0, 0),
+            reindeer(40, 100, 0, 0),
+        ];
+        let constraints = Constraints {
+            team_size: 2,
+            ..Default::default()
+        };
+        let team = best_team(&reindeers, constraints).unwrap();
+        assert_eq!(team.members, vec![1, 3]);
+        assert_eq!(team.strength, 90);
+    }
+
+    #[test]
+    fn test_infeasible() {
+        let reindeers = vec![reindeer(10, 100, 5, 3), reindeer(20, 150, 5, 3)];
+        let too_big = Constraints {
+            team_size: 3,
+            ..Default::default()
+        };
+        assert_eq!(best_team(&reindeers, too_big), None);
+
+        let too_spread = Constraints {
+            team_size: 2,
+            max_height_spread: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(best_team(&reindeers, too_spread), None);
+    }
+
+    #[test]
+    fn test_huge_candy_budget() {
+        let reindeers = vec![
+            reindeer(50, 100, 0, usize::MAX),
+            reindeer(40, 100, 0, 1),
+            reindeer(10, 100, 0, 0),
+        ];
+        let constraints = Constraints {
+            team_size: 2,
+            max_candies: Some(usize::MAX),
+            ..Default::default()
+        };
+        let team = best_team(&reindeers, constraints).unwrap();
+        assert_eq!(team.members, vec![0, 2]);
+        assert_eq!(team.candies, usize::MAX);
+
+        let unlimited = Constraints {
+            team_size: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            best_team(&reindeers, unlimited).unwrap().members,
+            vec![0, 2]
+        );
+    }
+
+    #[test]
+    fn test_huge_strength_and_magic() {
+        let half = usize::MAX / 2;
+        let reindeers = vec![
+            reindeer(half, 100, half, 1),
+            reindeer(half - 1, 100, half, 1),
+            reindeer(half, 100, half, 5),
+            reindeer(10, 100, 0, 0),
+        ];
+        let constraints = Constraints {
+            team_size: 3,
+            max_candies: Some(10),
+            min_snow_magic: Some(usize::MAX),
+            ..Default::default()
+        };
+        let team = best_team(&reindeers, constraints).unwrap();
+        assert_eq!(team.members, vec![0, 1, 2]);
+        assert_eq!(team.strength, 3 * half as u128 - 1);
+        assert_eq!(team.snow_magic, 3 * half as u128);
+
+        let budget = Constraints {
+            team_size: 2,
+            max_candies: Some(2),
+            ..Default::default()
+        };
+        let team = best_team(&reindeers, budget).unwrap();
+        assert_eq!(team.members, vec![0, 1]);
+        assert_eq!(team.strength, 2 * half as u128 - 1);
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        for seed in 0..40 {
+            let reindeers = herd(12, seed);
+            let constraints = Constraints {
+                team_size: 1 + (seed as usize % 5),
+                max_candies: Some(15 + seed as usize % 30),
+                min_snow_magic: Some(200 + 37 * seed as usize % 800),
+                max_height_spread: Some(10 + seed as usize % 40),
+            };
+            let found = best_team(&reindeers, constraints);
+            let expected = brute_force(&reindeers, constraints);
+            assert_eq!(found.as_ref().map(|t| t.strength), expected, "seed {seed}");
+
+            if let Some(team) = found {
+                assert_eq!(team.members.len(), constraints.team_size);
+                assert!(team.candies <= constraints.max_candies.unwrap());
+                assert!(team.snow_magic >= constraints.min_snow_magic.unwrap() as u128);
+            }
+        }
+    }
+
+    #[test]
+    fn test_anticorrelated_magic() {
+        for seed in 0..10 {
+            let reindeers = anticorrelated_herd(14, seed);
+            let constraints = Constraints {
+                team_size: 4,
+                min_snow_magic: Some(1800 + 50 * seed as usize),
+                ..Default::default()
+            };
+            let found = best_team(&reindeers, constraints);
+            let expected = brute_force(&reindeers, constraints);
+            assert_eq!(found.as_ref().map(|t| t.strength), expected, "seed {seed}");
+            if let Some(team) = found {
+                assert!(team.optimal);
+            }
+        }
+
+        // Used to take seconds, the magic knapsack cuts it to milliseconds
+        let reindeers = anticorrelated_herd(200, 1);
+        let constraints = Constraints {
+            team_size: 8,
+            min_snow_magic: Some(3000),
+            ..Default::default()
+        };
+        let team = best_team(&reindeers, constraints).unwrap();
+        assert!(team.optimal);
+        assert!(team.snow_magic >= 3000);
+    }
+
+    #[test]
+    fn test_large_herd() {
+        let reindeers = herd(300, 7);
+        let constraints = Constraints {
+            team_size: 9,
+            max_candies: Some(60),
+            min_snow_magic: Some(2500),
+            max_height_spread: Some(15),
+        };
+        let team = best_team(&reindeers, constraints).unwrap();
+        assert_eq!(team.members.len(), 9);
+        assert!(team.candies <= 60);
+        assert!(team.snow_magic >= 2500);
+    }
+}
@@ -1,45 +1,373 @@
+use rocket::data::{ByteUnit, Data, Limits};
+use rocket::http::Status;
+use rocket::response::status::BadRequest;
 use rocket::serde::json::Json;
-use rocket::{post, routes};
+use rocket::serde::{Deserialize, Serialize};
+use rocket::{post, routes, Responder};
+use rocket_dyn_templates::{context, Template};
 use std::collections::HashMap;
+use std::ops::Range;
+
+mod fuzzy;
+mod phrases;
+mod stream;
+mod text;
+
+use phrases::{OverlapMode, PhraseCounter};
+use stream::ElfStream;
+use text::{is_whole_word, Folded, MatchOptions};
 
 pub fn routes() -> Vec<rocket::Route> {
-    routes![elf_count]
+    routes![
+        elf_count,
+        elf_count_stream,
+        elf_count_fuzzy,
+        phrase_count,
+        elf_spans
+    ]
+}
+
+// Byte ranges of everything the elf counter looks for
+struct ElfMatches {
+    elf: Vec<Range<usize>>,
+    elf_on_a_shelf: Vec<Range<usize>>,
+    // Shelves that don't end an "elf on a shelf"
+    orphan_shelf: Vec<Range<usize>>,
+}
+
+const ELF_PHRASES: [&str; 3] = ["elf", "elf on a shelf", "shelf"];
+
+fn find_elves(raw: &str, options: &MatchOptions) -> ElfMatches {
+    let folded = Folded::new(raw, options);
+    let found = find_folded_elves(&folded, options);
+
+    let original = |ranges: Vec<Range<usize>>| ranges.iter().map(|r| folded.original(r)).collect();
+    ElfMatches {
+        elf: original(found.elf),
+        elf_on_a_shelf: original(found.elf_on_a_shelf),
+        orphan_shelf: original(found.orphan_shelf),
+    }
+}
+
+// Matches on the folded text, so every option applies to all three counters alike
+fn find_folded_elves(folded: &Folded, options: &MatchOptions) -> ElfMatches {
+    let phrases = ELF_PHRASES.map(String::from);
+    let counter = PhraseCounter::new(&phrases, OverlapMode::Overlapping).unwrap();
+
+    let mut found = ElfMatches {
+        elf: vec![],
+        elf_on_a_shelf: vec![],
+        orphan_shelf: vec![],
+    };
+    let mut shelves = vec![];
+    for m in counter.find(folded.text()) {
+        let range = m.start..m.end;
+        if options.word_boundary && !is_whole_word(folded.text(), &range) {
+            continue;
+        }
+        match m.phrase {
+            0 => found.elf.push(range),
+            1 => found.elf_on_a_shelf.push(range),
+            _ => shelves.push(range),
+        }
+    }
+    found.orphan_shelf = shelves
+        .into_iter()
+        .filter(|shelf| !found.elf_on_a_shelf.iter().any(|e| e.end == shelf.end))
+        .collect();
+
+    found
 }
 
-#[post("/", data = "<raw>")]
-fn elf_count(raw: &str) -> Json<HashMap<String, usize>> {
-    let elf_count = raw.matches("elf").count();
-    let elf_on_a_shelf_count = count_overlapping_occurrences(raw, "elf on a shelf");
-    let shelf_count = raw.matches("shelf").count();
+#[post("/?<options..>", data = "<raw>")]
+fn elf_count(raw: &str, options: MatchOptions) -> Json<HashMap<String, usize>> {
+    let found = find_elves(raw, &options);
 
     let mut result = HashMap::new();
-    result.insert("elf".to_string(), elf_count);
-    result.insert("elf on a shelf".to_string(), elf_on_a_shelf_count);
+    result.insert("elf".to_string(), found.elf.len());
+    result.insert("elf on a shelf".to_string(), found.elf_on_a_shelf.len());
     result.insert(
         "shelf with no elf on it".to_string(),
-        shelf_count - elf_on_a_shelf_count,
+        found.orphan_shelf.len(),
     );
 
     println!("@elf_count: {raw} => {:?}", result);
     Json(result)
 }
 
-fn count_overlapping_occurrences(haystack: &str, needle: &str) -> usize {
-    let mut count = 0;
-    let needle_len = needle.len();
+// Default cap on a streamed body, override with the `elves` limit
+const STREAM_LIMIT: ByteUnit = ByteUnit::Gibibyte(8);
+const STREAM_CHUNK: usize = 64 * 1024;
+
+// Same counts as `elf_count`, without holding the body in memory
+#[post("/stream?<options..>", data = "<data>")]
+async fn elf_count_stream(
+    data: Data<'_>,
+    options: MatchOptions,
+    limits: &Limits,
+) -> Result<Json<HashMap<String, usize>>, Status> {
+    use tokio::io::AsyncReadExt;
+
+    let limit = limits.get("elves").unwrap_or(STREAM_LIMIT);
+    let mut body = data.open(limit);
+    let mut elves = ElfStream::new(options);
+    let mut chunk = vec![0; STREAM_CHUNK];
+    let mut bytes_read = 0;
+
+    loop {
+        let read = body.read(&mut chunk).await.map_err(|e| {
+            println!("Failed to read elf stream: {e}");
+            Status::BadRequest
+        })?;
+        if read == 0 {
+            break;
+        }
+        bytes_read += read as u64;
+        elves.feed(&chunk[..read]).map_err(|e| {
+            println!("@elf_count_stream: {e}");
+            Status::BadRequest
+        })?;
+    }
+
+    // The stream silently stops at the limit, so a body that reaches it was truncated
+    if bytes_read >= limit.as_u64() {
+        println!("Elf stream exceeds {} bytes", limit.as_u64());
+        return Err(Status::PayloadTooLarge);
+    }
 
-    // Iterate over each possible starting position in `haystack`
-    for start in 0..=haystack.len() {
-        // Check if the remaining string is long enough to contain `needle`
-        if start + needle_len <= haystack.len() {
-            // If the substring starting at `start` matches `needle`, increment `count`
-            if &haystack[start..start + needle_len] == needle {
-                count += 1;
+    let counts = elves.finish().map_err(|e| {
+        println!("@elf_count_stream: {e}");
+        Status::BadRequest
+    })?;
+    let keys = ["elf", "elf on a shelf", "shelf with no elf on it"];
+    let result: HashMap<String, usize> = keys.map(String::from).into_iter().zip(counts).collect();
+
+    println!("@elf_count_stream: {bytes_read} bytes => {:?}", result);
+    Ok(Json(result))
+}
+
+// Beyond this even "elf on a shelf" starts turning up in ordinary sentences
+const MAX_FUZZY_DISTANCE: usize = 2;
+
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct FuzzyCount {
+    exact: usize,
+    // Within the distance but not exact, and not overlapping an exact match of the same phrase
+    fuzzy: usize,
+}
+
+// Exact counts are the same as `elf_count`'s
+#[post("/fuzzy?<distance>&<options..>", data = "<raw>")]
+fn elf_count_fuzzy(
+    raw: &str,
+    distance: Option<&str>,
+    options: MatchOptions,
+) -> Result<Json<HashMap<String, FuzzyCount>>, BadRequest<String>> {
+    let distance = match distance {
+        None => 1,
+        Some(distance) => match distance.parse::<usize>() {
+            Ok(distance) if distance <= MAX_FUZZY_DISTANCE => distance,
+            _ => {
+                return Err(BadRequest(format!(
+                    "Invalid distance {distance}, expected 0 to {MAX_FUZZY_DISTANCE}"
+                )))
             }
+        },
+    };
+
+    let folded = Folded::new(raw, &options);
+    let exact = find_folded_elves(&folded, &options);
+
+    // Every phrase takes the full distance, but a phrase more than a quarter wrong only counts as
+    // a whole word: "e1f" and "elff" pass for "elf", the "elp" in "help" doesn't
+    let fuzzy = |phrase: &str, exact: &[Range<usize>]| -> Vec<Range<usize>> {
+        let found = match distance > phrase.chars().count() / 4 {
+            true => fuzzy::find_approximate_words(folded.text(), phrase, distance),
+            false => fuzzy::find_approximate(folded.text(), phrase, distance),
+        };
+        found
+            .into_iter()
+            .filter(|r| !exact.iter().any(|e| e.start < r.end && r.start < e.end))
+            .filter(|r| !options.word_boundary || is_whole_word(folded.text(), r))
+            .collect()
+    };
+    let fuzzy_elf = fuzzy("elf", &exact.elf);
+    let fuzzy_elf_on_a_shelf = fuzzy("elf on a shelf", &exact.elf_on_a_shelf);
+    let exact_shelves: Vec<Range<usize>> = exact
+        .elf_on_a_shelf
+        .iter()
+        .map(|e| e.end - "shelf".len()..e.end)
+        .chain(exact.orphan_shelf.iter().cloned())
+        .collect();
+    let fuzzy_orphan_shelf = fuzzy("shelf", &exact_shelves)
+        .into_iter()
+        .filter(|shelf| {
+            !exact
+                .elf_on_a_shelf
+                .iter()
+                .chain(&fuzzy_elf_on_a_shelf)
+                .any(|e| e.start <= shelf.start && shelf.end <= e.end)
+        })
+        .count();
+
+    let mut result = HashMap::new();
+    result.insert(
+        "elf".to_string(),
+        FuzzyCount {
+            exact: exact.elf.len(),
+            fuzzy: fuzzy_elf.len(),
+        },
+    );
+    result.insert(
+        "elf on a shelf".to_string(),
+        FuzzyCount {
+            exact: exact.elf_on_a_shelf.len(),
+            fuzzy: fuzzy_elf_on_a_shelf.len(),
+        },
+    );
+    result.insert(
+        "shelf with no elf on it".to_string(),
+        FuzzyCount {
+            exact: exact.orphan_shelf.len(),
+            fuzzy: fuzzy_orphan_shelf,
+        },
+    );
+
+    println!("@elf_count_fuzzy: {} bytes => {:?}", raw.len(), result);
+    Ok(Json(result))
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct CountRequest {
+    text: String,
+    phrases: Vec<String>,
+    #[serde(default)]
+    mode: OverlapMode,
+}
+
+#[post("/count", data = "<request>")]
+fn phrase_count(
+    request: Json<CountRequest>,
+) -> Result<Json<HashMap<String, usize>>, BadRequest<String>> {
+    let counter = PhraseCounter::new(&request.phrases, request.mode).map_err(BadRequest)?;
+    let counts = counter.count(&request.text);
+
+    let result = counter.phrases().iter().cloned().zip(counts).collect();
+    Ok(Json(result))
+}
+
+// Half-open offsets, both in bytes and in chars
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Span {
+    start: usize,
+    end: usize,
+    char_start: usize,
+    char_end: usize,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct ElfSpans {
+    elf: Vec<Span>,
+    #[serde(rename = "elf on a shelf")]
+    elf_on_a_shelf: Vec<Span>,
+    #[serde(rename = "shelf with no elf on it")]
+    orphan_shelf: Vec<Span>,
+}
+
+impl ElfSpans {
+    fn new(raw: &str, found: &ElfMatches) -> ElfSpans {
+        // Char index at every byte offset that starts a char, plus the end of the text
+        let mut char_index = vec![0; raw.len() + 1];
+        for (i, (byte, _)) in raw.char_indices().enumerate() {
+            char_index[byte] = i;
+        }
+        char_index[raw.len()] = raw.chars().count();
+
+        let spans = |ranges: &[Range<usize>]| {
+            ranges
+                .iter()
+                .map(|r| Span {
+                    start: r.start,
+                    end: r.end,
+                    char_start: char_index[r.start],
+                    char_end: char_index[r.end],
+                })
+                .collect()
+        };
+        ElfSpans {
+            elf: spans(&found.elf),
+            elf_on_a_shelf: spans(&found.elf_on_a_shelf),
+            orphan_shelf: spans(&found.orphan_shelf),
         }
     }
+}
 
-    count
+// A run of text covered by the same set of categories
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct Segment<'a> {
+    text: &'a str,
+    // Space separated CSS classes, empty for plain text
+    classes: String,
+}
+
+fn highlight<'a>(raw: &'a str, found: &ElfMatches) -> Vec<Segment<'a>> {
+    let categories = [
+        ("elf", &found.elf),
+        ("elf-on-a-shelf", &found.elf_on_a_shelf),
+        ("orphan-shelf", &found.orphan_shelf),
+    ];
+
+    let mut boundaries = vec![0, raw.len()];
+    for (_, ranges) in categories {
+        boundaries.extend(ranges.iter().flat_map(|r| [r.start, r.end]));
+    }
+    boundaries.sort_unstable();
+    boundaries.dedup();
+
+    boundaries
+        .windows(2)
+        .map(|w| {
+            let classes: Vec<&str> = categories
+                .iter()
+                .filter(|(_, ranges)| ranges.iter().any(|r| r.start <= w[0] && w[1] <= r.end))
+                .map(|(class, _)| *class)
+                .collect();
+            Segment {
+                text: &raw[w[0]..w[1]],
+                classes: classes.join(" "),
+            }
+        })
+        .collect()
+}
+
+#[derive(Responder)]
+enum SpansResponse {
+    Json(Json<ElfSpans>),
+    Html(Template),
+}
+
+// `?format=html` renders the text with every category highlighted
+#[post("/spans?<format>&<options..>", data = "<raw>")]
+fn elf_spans(
+    raw: &str,
+    format: Option<&str>,
+    options: MatchOptions,
+) -> Result<SpansResponse, BadRequest<String>> {
+    let found = find_elves(raw, &options);
+
+    match format.unwrap_or("json") {
+        "json" => Ok(SpansResponse::Json(Json(ElfSpans::new(raw, &found)))),
+        "html" => Ok(SpansResponse::Html(Template::render(
+            "elf_highlight",
+            context! { segments: highlight(raw, &found) },
+        ))),
+        other => Err(BadRequest(format!("Unknown format {other}"))),
+    }
 }
 
 #[cfg(test)]
@@ -49,71 +377,309 @@ mod tests {
     #[test]
     fn test_no_elf() {
         let input = "This string does not contain the magic word.";
-        let response = elf_count(input);
+        let response = elf_count(input, MatchOptions::default());
         assert_eq!(response.0.get("elf").unwrap(), &0);
     }
 
     #[test]
     fn test_single_elf() {
         let input = "An elf walked into the room.";
-        let response = elf_count(input);
+        let response = elf_count(input, MatchOptions::default());
         assert_eq!(response.0.get("elf").unwrap(), &1);
     }
 
     #[test]
     fn test_multiple_elves() {
         let input = "The elf saw another elf.";
-        let response = elf_count(input);
+        let response = elf_count(input, MatchOptions::default());
         assert_eq!(response.0.get("elf").unwrap(), &2);
     }
 
     #[test]
     fn test_no_spaces_required() {
         let input = "elfelfelf.";
-        let response = elf_count(input);
+        let response = elf_count(input, MatchOptions::default());
         assert_eq!(response.0.get("elf").unwrap(), &3);
     }
 
     #[test]
     fn test_elf_in_other_words() {
         let input = "The shelf had an elfin figurine.";
-        let response = elf_count(input);
+        let response = elf_count(input, MatchOptions::default());
         assert_eq!(response.0.get("elf").unwrap(), &2); // "shelf" and "elfin"
     }
 
     #[test]
     fn test_empty_string() {
         let input = "";
-        let response = elf_count(input);
+        let response = elf_count(input, MatchOptions::default());
         assert_eq!(response.0.get("elf").unwrap(), &0);
     }
 
     #[test]
     fn test_only_elf() {
         let input = "elf";
-        let response = elf_count(input);
+        let response = elf_count(input, MatchOptions::default());
         assert_eq!(response.0.get("elf").unwrap(), &1);
     }
 
     #[test]
     fn test_elf_on_a_shelf() {
         let input = "An elf on a shelf and another elf on a shelf were talking.";
-        let response = elf_count(input);
+        let response = elf_count(input, MatchOptions::default());
         assert_eq!(response.0.get("elf on a shelf").unwrap(), &2);
     }
 
     #[test]
     fn test_shelf_with_no_elf_on_it() {
         let input = "There is a shelf with no elf on it, and another shelf over there.";
-        let response = elf_count(input);
+        let response = elf_count(input, MatchOptions::default());
         assert_eq!(response.0.get("shelf with no elf on it").unwrap(), &2);
     }
 
     #[test]
     fn test_elf_on_a_shelf_and_not_on_a_shelf() {
         let input = "An elf on a shelf and another elf on a shelf were talking. Furthermore, there is a shelf with no elf on it, and another shelf over there.";
-        let response = elf_count(input);
+        let response = elf_count(input, MatchOptions::default());
         assert_eq!(response.0.get("elf on a shelf").unwrap(), &2);
         assert_eq!(response.0.get("shelf with no elf on it").unwrap(), &2);
     }
+
+    #[test]
+    fn test_phrase_count_route() {
+        use rocket::http::Status;
+        use rocket::local::blocking::Client;
+
+        let rocket = rocket::build().mount("/6", routes());
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+
+        let response = client
+            .post("/6/count")
+            .body(r#"{"text":"elf on a shelf on a shelf","phrases":["elf","on a shelf"],"mode":"leftmost_longest"}"#)
+            .dispatch();
+        let counts: HashMap<String, usize> = response.into_json().unwrap();
+        assert_eq!(counts.get("elf").unwrap(), &1);
+        assert_eq!(counts.get("on a shelf").unwrap(), &2);
+
+        let response = client
+            .post("/6/count")
+            .body(r#"{"text":"elf","phrases":[""]}"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let response = client
+            .post("/6/count")
+            .body(r#"{"text":"elf","phrases":["elf"],"mode":"sideways"}"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::UnprocessableEntity);
+    }
+
+    #[test]
+    fn test_orphan_shelf_spans() {
+        let input = "elf on a shelf, shelf";
+        let found = find_elves(input, &MatchOptions::default());
+        assert_eq!(found.elf, vec![0..3, 11..14, 18..21]);
+        assert_eq!(found.elf_on_a_shelf, vec![0..14]);
+        assert_eq!(found.orphan_shelf, vec![16..21]);
+    }
+
+    #[test]
+    fn test_char_offsets() {
+        let input = "ünïcode elf";
+        let spans = ElfSpans::new(input, &find_elves(input, &MatchOptions::default()));
+        assert_eq!(
+            spans.elf,
+            vec![Span {
+                start: 10,
+                end: 13,
+                char_start: 8,
+                char_end: 11
+            }]
+        );
+    }
+
+    #[test]
+    fn test_highlight_segments() {
+        let input = "an elf on a shelf";
+        let segments = highlight(input, &find_elves(input, &MatchOptions::default()));
+        let rendered: Vec<(&str, &str)> = segments
+            .iter()
+            .map(|s| (s.text, s.classes.as_str()))
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                ("an ", ""),
+                ("elf", "elf elf-on-a-shelf"),
+                (" on a sh", "elf-on-a-shelf"),
+                ("elf", "elf elf-on-a-shelf"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_spans_route() {
+        use rocket::http::{ContentType, Status};
+        use rocket::local::blocking::Client;
+
+        let rocket = rocket::build()
+            .mount("/6", routes())
+            .attach(Template::fairing());
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+
+        let response = client.post("/6/spans").body("shelf <elf>").dispatch();
+        let spans: ElfSpans = response.into_json().unwrap();
+        assert_eq!(spans.elf.len(), 2);
+        assert_eq!(spans.orphan_shelf[0].end, 5);
+
+        let response = client
+            .post("/6/spans?format=html")
+            .body("shelf <elf>")
+            .dispatch();
+        assert_eq!(response.content_type(), Some(ContentType::HTML));
+        let html = response.into_string().unwrap();
+        assert!(html.contains(r#"<mark class="orphan-shelf">sh</mark>"#));
+        assert!(html.contains(r#"&lt;<mark class="elf">elf</mark>&gt;"#));
+
+        let response = client.post("/6/spans?format=pdf").body("elf").dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn test_match_options() {
+        let input = "Elf, ＥＬＦ and an elf on a Shelf, but no elfin shelf";
+        let count = |case_insensitive, nfkc, word_boundary| {
+            let options = MatchOptions {
+                case_insensitive,
+                nfkc,
+                word_boundary,
+            };
+            let result = elf_count(input, options).0;
+            [
+                result["elf"],
+                result["elf on a shelf"],
+                result["shelf with no elf on it"],
+            ]
+        };
+
+        assert_eq!(count(false, false, false), [4, 0, 1]);
+        assert_eq!(count(true, false, false), [5, 1, 1]);
+        assert_eq!(count(true, true, false), [6, 1, 1]);
+        assert_eq!(count(true, true, true), [3, 1, 1]);
+        assert_eq!(count(false, false, true), [1, 0, 1]);
+    }
+
+    #[test]
+    fn test_match_options_route() {
+        use rocket::local::blocking::Client;
+
+        let rocket = rocket::build()
+            .mount("/6", routes())
+            .attach(Template::fairing());
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+
+        let response = client
+            .post("/6?case_insensitive=true&nfkc=true&word_boundary=true")
+            .body("ＥＬＦ on a shelf")
+            .dispatch();
+        let counts: HashMap<String, usize> = response.into_json().unwrap();
+        assert_eq!(counts.get("elf on a shelf").unwrap(), &1);
+
+        let response = client
+            .post("/6/spans?case_insensitive=true&nfkc=true")
+            .body("ＥＬＦ elf")
+            .dispatch();
+        let spans: ElfSpans = response.into_json().unwrap();
+        assert_eq!(spans.elf[0].end, 9);
+        assert_eq!(spans.elf[1].char_start, 4);
+    }
+
+    #[test]
+    fn test_stream_route() {
+        use rocket::local::blocking::Client;
+
+        let rocket = rocket::build()
+            .mount("/6", routes())
+            .attach(Template::fairing());
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+
+        let body = "An elf on a shelf and a shelf. ".repeat(10_000);
+        let response = client.post("/6/stream").body(&body).dispatch();
+        let counts: HashMap<String, usize> = response.into_json().unwrap();
+        assert_eq!(counts, elf_count(&body, MatchOptions::default()).0);
+        assert_eq!(counts.get("shelf with no elf on it").unwrap(), &10_000);
+
+        let response = client
+            .post("/6/stream")
+            .body(b"elf \xff".as_slice())
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn test_fuzzy_counts() {
+        let input = "An e1f on a she1f, an elff and a shelf.";
+        let counts = elf_count_fuzzy(input, None, MatchOptions::default())
+            .unwrap()
+            .0;
+
+        // "elff" holds an exact "elf", so only "e1f" is fuzzy, and the "e1f" in "she1f" isn't a word
+        assert_eq!(counts["elf"], FuzzyCount { exact: 2, fuzzy: 1 });
+        assert_eq!(counts["elf on a shelf"], FuzzyCount { exact: 0, fuzzy: 0 });
+        assert_eq!(
+            counts["shelf with no elf on it"],
+            FuzzyCount { exact: 1, fuzzy: 1 }
+        );
+
+        // With word boundaries nothing in "elff" is exact, so it's fuzzy too
+        let whole_words = MatchOptions {
+            word_boundary: true,
+            ..MatchOptions::default()
+        };
+        let counts = elf_count_fuzzy(input, None, whole_words).unwrap().0;
+        assert_eq!(counts["elf"], FuzzyCount { exact: 0, fuzzy: 2 });
+        assert_eq!(
+            counts["shelf with no elf on it"],
+            FuzzyCount { exact: 1, fuzzy: 1 }
+        );
+
+        // Two edits find the whole phrase, whose "she1f" then isn't an orphan
+        let counts = elf_count_fuzzy(input, Some("2"), MatchOptions::default())
+            .unwrap()
+            .0;
+        assert_eq!(counts["elf on a shelf"], FuzzyCount { exact: 0, fuzzy: 1 });
+        assert_eq!(
+            counts["shelf with no elf on it"],
+            FuzzyCount { exact: 1, fuzzy: 0 }
+        );
+
+        let counts = elf_count_fuzzy(input, Some("0"), MatchOptions::default())
+            .unwrap()
+            .0;
+        assert_eq!(counts["elf"], FuzzyCount { exact: 2, fuzzy: 0 });
+
+        assert!(elf_count_fuzzy(input, Some("3"), MatchOptions::default()).is_err());
+        assert!(elf_count_fuzzy(input, Some("one"), MatchOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_fuzzy_counts_plain_prose() {
+        let input = "Help! Or else the yellow half of the cake sat by itself, so she left it.";
+        let counts = elf_count_fuzzy(input, None, MatchOptions::default())
+            .unwrap()
+            .0;
+
+        // The "elf" in "itself" is exact, as elf_count would find it
+        assert_eq!(counts["elf"], FuzzyCount { exact: 1, fuzzy: 0 });
+        assert_eq!(counts["elf on a shelf"], FuzzyCount::default());
+        assert_eq!(counts["shelf with no elf on it"], FuzzyCount::default());
+
+        // Two edits is most of "elf", so "Help", "else" and "half" are what was asked for
+        let counts = elf_count_fuzzy(input, Some("2"), MatchOptions::default())
+            .unwrap()
+            .0;
+        assert_eq!(counts["elf"], FuzzyCount { exact: 1, fuzzy: 3 });
+        assert_eq!(counts["elf on a shelf"], FuzzyCount::default());
+        assert_eq!(counts["shelf with no elf on it"], FuzzyCount::default());
+    }
 }
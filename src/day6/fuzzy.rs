@@ -0,0 +1,185 @@
+use std::ops::Range;
+
+// Byte ranges of non-overlapping occurrences of `pattern` within `max_distance` edits, scanning
+// left to right. Of a run of neighbouring end positions that all match, the first closest one wins.
+// Matches have to be at least as long as the pattern: the text either side of a match is already
+// free, so dropping letters too would let "el" or "lf" pass for "elf".
+pub fn find_approximate(text: &str, pattern: &str, max_distance: usize) -> Vec<Range<usize>> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let byte_offset = |i: usize| chars.get(i).map_or(text.len(), |&(offset, _)| offset);
+
+    let mut found = vec![];
+    let mut from = 0;
+    while from < chars.len() {
+        // Edit distance of each pattern prefix against the best substring ending here, and
+        // the char index that substring starts at
+        let mut distance: Vec<usize> = (0..=pattern.len()).collect();
+        let mut start = vec![from; pattern.len() + 1];
+        // (start, end, distance) in chars
+        let mut best: Option<(usize, usize, usize)> = None;
+
+        for (j, &(_, c)) in chars.iter().enumerate().skip(from) {
+            let mut next_distance = vec![0; pattern.len() + 1];
+            let mut next_start = vec![j + 1; pattern.len() + 1];
+            for i in 1..=pattern.len() {
+                let substitute = distance[i - 1] + usize::from(pattern[i - 1] != c);
+                let skip_text = distance[i] + 1;
+                let skip_pattern = next_distance[i - 1] + 1;
+                (next_distance[i], next_start[i]) = if substitute <= skip_text.min(skip_pattern) {
+                    (substitute, start[i - 1])
+                } else if skip_text <= skip_pattern {
+                    (skip_text, start[i])
+                } else {
+                    (skip_pattern, next_start[i - 1])
+                };
+            }
+            distance = next_distance;
+            start = next_start;
+
+            let d = distance[pattern.len()];
+            let long_enough = j + 1 - start[pattern.len()] >= pattern.len();
+            if d <= max_distance && long_enough {
+                let closer = match best {
+                    Some((_, _, best)) => d < best,
+                    None => true,
+                };
+                if closer {
+                    best = Some((start[pattern.len()], j + 1, d));
+                }
+            } else if best.is_some() {
+                break;
+            }
+        }
+
+        match best {
+            Some((start, end, _)) => {
+                found.push(byte_offset(start)..byte_offset(end));
+                from = end;
+            }
+            None => break,
+        }
+    }
+
+    found
+}
+
+// Like `find_approximate`, but a match has to run from the start of a word to the end of one, so
+// "elff" passes for "elf" while the "elf" in "shelf" or the "el" in "el," don't. Of the matches
+// starting at a word, the first closest one wins.
+pub fn find_approximate_words(text: &str, pattern: &str, max_distance: usize) -> Vec<Range<usize>> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let byte_offset = |i: usize| chars.get(i).map_or(text.len(), |&(offset, _)| offset);
+    let is_word_char = |i: usize| chars.get(i).is_some_and(|&(_, c)| c.is_alphanumeric());
+
+    let mut found = vec![];
+    let mut from = 0;
+    while from < chars.len() {
+        if !is_word_char(from) || (from > 0 && is_word_char(from - 1)) {
+            from += 1;
+            continue;
+        }
+
+        // Edit distance of each pattern prefix against text[from..=j]
+        let mut distance: Vec<usize> = (0..=pattern.len()).collect();
+        // (end, distance) in chars
+        let mut best: Option<(usize, usize)> = None;
+
+        for (j, &(_, c)) in chars.iter().enumerate().skip(from) {
+            let mut next_distance = vec![distance[0] + 1; pattern.len() + 1];
+            for i in 1..=pattern.len() {
+                next_distance[i] = (distance[i - 1] + usize::from(pattern[i - 1] != c))
+                    .min(distance[i] + 1)
+                    .min(next_distance[i - 1] + 1);
+            }
+            distance = next_distance;
+
+            let d = distance[pattern.len()];
+            let ends_word = is_word_char(j) && !is_word_char(j + 1);
+            let long_enough = j + 1 - from >= pattern.len();
+            if d <= max_distance && ends_word && long_enough {
+                let closer = match best {
+                    Some((_, best)) => d < best,
+                    None => true,
+                };
+                if closer {
+                    best = Some((j + 1, d));
+                }
+            }
+            // Once every prefix is out of reach, longer text only gets further away
+            if distance.iter().all(|&d| d > max_distance) {
+                break;
+            }
+        }
+
+        match best {
+            Some((end, _)) => {
+                found.push(byte_offset(from)..byte_offset(end));
+                from = end;
+            }
+            None => from += 1,
+        }
+    }
+
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find<'a>(text: &'a str, pattern: &str, max_distance: usize) -> Vec<&'a str> {
+        find_approximate(text, pattern, max_distance)
+            .into_iter()
+            .map(|range| &text[range])
+            .collect()
+    }
+
+    #[test]
+    fn test_exact() {
+        assert_eq!(find("an elf, elfelf", "elf", 0), vec!["elf"; 3]);
+        assert_eq!(find("no elves", "elf", 0), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_ocr_typos() {
+        assert_eq!(find("e1f", "elf", 1), vec!["e1f"]);
+        assert_eq!(find("elff", "elf", 1), vec!["elf"]);
+        assert_eq!(find("she1f", "shelf", 1), vec!["she1f"]);
+        assert_eq!(find("she1f", "shelf", 0), Vec::<&str>::new());
+        assert_eq!(
+            find("an e1f on a she1f", "elf on a shelf", 2),
+            vec!["e1f on a she1f"]
+        );
+    }
+
+    #[test]
+    fn test_closest_in_run() {
+        // "elfx" is within one edit too, but the exact match a char earlier is closer
+        assert_eq!(find("xelfx", "elf", 1), vec!["elf"]);
+        assert_eq!(find("ëlf élf", "elf", 1), vec!["ëlf", "élf"]);
+    }
+
+    #[test]
+    fn test_whole_words() {
+        let find = |text, max_distance| -> Vec<&str> {
+            find_approximate_words(text, "elf", max_distance)
+                .into_iter()
+                .map(|range| &text[range])
+                .collect()
+        };
+        assert_eq!(find("an e1f, an elff", 1), vec!["e1f", "elff"]);
+        assert_eq!(find("a shelf, she1f, el, or elves", 1), Vec::<&str>::new());
+        assert_eq!(find("help, half", 1), Vec::<&str>::new());
+        assert_eq!(find("help, half", 2), vec!["help", "half"]);
+    }
+
+    #[test]
+    fn test_too_short() {
+        for fragment in ["el", "lf", "ef"] {
+            assert_eq!(find(fragment, "elf", 1), Vec::<&str>::new());
+        }
+        assert_eq!(find("by itself", "shelf", 1), Vec::<&str>::new());
+    }
+}
@@ -0,0 +1,167 @@
+use aho_corasick::{AhoCorasick, MatchKind};
+use rocket::serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(crate = "rocket::serde", rename_all = "snake_case")]
+pub enum OverlapMode {
+    // Every occurrence of every phrase, even inside or across other matches
+    #[default]
+    Overlapping,
+    // Scanning left to right, the first phrase to complete wins and the scan resumes after it
+    NonOverlapping,
+    // Scanning left to right, the longest phrase starting at the earliest position wins
+    LeftmostLongest,
+}
+
+// Byte range of one occurrence of the phrase at index `phrase`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhraseMatch {
+    pub phrase: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+// Counts a fixed set of phrases in a single pass over the text
+pub struct PhraseCounter {
+    phrases: Vec<String>,
+    automaton: AhoCorasick,
+    mode: OverlapMode,
+}
+
+impl PhraseCounter {
+    pub fn new(phrases: &[String], mode: OverlapMode) -> Result<PhraseCounter, String> {
+        let mut unique: Vec<String> = Vec::with_capacity(phrases.len());
+        for phrase in phrases {
+            if phrase.is_empty() {
+                return Err("Phrases must not be empty".to_string());
+            }
+            if !unique.contains(phrase) {
+                unique.push(phrase.clone());
+            }
+        }
+
+        let match_kind = match mode {
+            OverlapMode::Overlapping | OverlapMode::NonOverlapping => MatchKind::Standard,
+            OverlapMode::LeftmostLongest => MatchKind::LeftmostLongest,
+        };
+        let automaton = AhoCorasick::builder()
+            .match_kind(match_kind)
+            .build(&unique)
+            .map_err(|e| format!("Failed to build automaton: {e}"))?;
+
+        Ok(PhraseCounter {
+            phrases: unique,
+            automaton,
+            mode,
+        })
+    }
+
+    pub fn phrases(&self) -> &[String] {
+        &self.phrases
+    }
+
+    // Counts per phrase, in the order of `phrases()`, without collecting the matches
+    pub fn count(&self, text: &str) -> Vec<usize> {
+        let mut counts = vec![0; self.phrases.len()];
+        match self.mode {
+            OverlapMode::Overlapping => {
+                for m in self.automaton.find_overlapping_iter(text) {
+                    counts[m.pattern().as_usize()] += 1;
+                }
+            }
+            OverlapMode::NonOverlapping | OverlapMode::LeftmostLongest => {
+                for m in self.automaton.find_iter(text) {
+                    counts[m.pattern().as_usize()] += 1;
+                }
+            }
+        }
+        counts
+    }
+
+    // Every match in order of where it ends
+    pub fn find(&self, text: &str) -> Vec<PhraseMatch> {
+        let to_match = |m: aho_corasick::Match| PhraseMatch {
+            phrase: m.pattern().as_usize(),
+            start: m.start(),
+            end: m.end(),
+        };
+        match self.mode {
+            OverlapMode::Overlapping => self
+                .automaton
+                .find_overlapping_iter(text)
+                .map(to_match)
+                .collect(),
+            OverlapMode::NonOverlapping | OverlapMode::LeftmostLongest => {
+                self.automaton.find_iter(text).map(to_match).collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(phrases: &[&str], mode: OverlapMode, text: &str) -> Vec<usize> {
+        let phrases: Vec<String> = phrases.iter().map(|p| p.to_string()).collect();
+        PhraseCounter::new(&phrases, mode).unwrap().count(text)
+    }
+
+    #[test]
+    fn test_overlapping() {
+        assert_eq!(count(&["aa"], OverlapMode::Overlapping, "aaaa"), vec![3]);
+        assert_eq!(
+            count(&["elf", "shelf"], OverlapMode::Overlapping, "shelf elf"),
+            vec![2, 1]
+        );
+    }
+
+    #[test]
+    fn test_non_overlapping() {
+        assert_eq!(count(&["aa"], OverlapMode::NonOverlapping, "aaaa"), vec![2]);
+        // "b" completes before "abcd" would, so "abcd" is never seen
+        assert_eq!(
+            count(&["abcd", "b"], OverlapMode::NonOverlapping, "abcd"),
+            vec![0, 1]
+        );
+    }
+
+    #[test]
+    fn test_leftmost_longest() {
+        assert_eq!(
+            count(&["abcd", "b"], OverlapMode::LeftmostLongest, "abcd"),
+            vec![1, 0]
+        );
+        assert_eq!(
+            count(
+                &["elf", "elf on a shelf"],
+                OverlapMode::LeftmostLongest,
+                "elf on a shelf, elf"
+            ),
+            vec![1, 1]
+        );
+    }
+
+    #[test]
+    fn test_find() {
+        let phrases = vec!["elf".to_string(), "shelf".to_string()];
+        let counter = PhraseCounter::new(&phrases, OverlapMode::Overlapping).unwrap();
+        let found: Vec<(usize, usize, usize)> = counter
+            .find("shelf elf")
+            .into_iter()
+            .map(|m| (m.phrase, m.start, m.end))
+            .collect();
+        assert_eq!(found, vec![(1, 0, 5), (0, 2, 5), (0, 6, 9)]);
+    }
+
+    #[test]
+    fn test_duplicate_and_empty_phrases() {
+        let phrases = vec!["elf".to_string(), "elf".to_string()];
+        let counter = PhraseCounter::new(&phrases, OverlapMode::Overlapping).unwrap();
+        assert_eq!(counter.phrases(), ["elf"]);
+        assert_eq!(counter.count("elfelf"), vec![2]);
+
+        let phrases = vec!["".to_string()];
+        assert!(PhraseCounter::new(&phrases, OverlapMode::Overlapping).is_err());
+    }
+}
@@ -0,0 +1,142 @@
+use super::phrases::{OverlapMode, PhraseCounter};
+use super::text::{is_whole_word, Folded, MatchOptions};
+use super::ELF_PHRASES;
+
+// Longest a single char gets in UTF-8
+const MAX_CHAR_LEN: usize = 4;
+
+// Counts elves in text fed a chunk at a time, keeping only a few bytes between chunks
+pub struct ElfStream {
+    counter: PhraseCounter,
+    options: MatchOptions,
+    longest_phrase: usize,
+    // Leading bytes of a char split across chunks
+    partial: Vec<u8>,
+    // Folded text not yet scanned, plus enough before it for matches that straddle chunks
+    window: String,
+    // Matches ending at or before this offset into `window` are already counted
+    counted: usize,
+    // Per phrase, in `ELF_PHRASES` order
+    counts: [usize; 3],
+}
+
+impl ElfStream {
+    pub fn new(options: MatchOptions) -> ElfStream {
+        let phrases = ELF_PHRASES.map(String::from);
+        ElfStream {
+            counter: PhraseCounter::new(&phrases, OverlapMode::Overlapping).unwrap(),
+            options,
+            longest_phrase: phrases.iter().map(String::len).max().unwrap(),
+            partial: vec![],
+            window: String::new(),
+            counted: 0,
+            counts: [0; 3],
+        }
+    }
+
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), String> {
+        self.partial.extend_from_slice(chunk);
+        let valid = match std::str::from_utf8(&self.partial) {
+            Ok(text) => text.len(),
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            Err(e) => return Err(format!("Invalid UTF-8: {e}")),
+        };
+
+        let text = std::str::from_utf8(&self.partial[..valid]).unwrap();
+        self.window
+            .push_str(Folded::new(text, &self.options).text());
+        self.partial.drain(..valid);
+
+        self.scan(false);
+        Ok(())
+    }
+
+    // Counts of elf, elf on a shelf and shelf with no elf on it
+    pub fn finish(mut self) -> Result<[usize; 3], String> {
+        if !self.partial.is_empty() {
+            return Err("Body ends in the middle of a UTF-8 character".to_string());
+        }
+
+        self.scan(true);
+        let [elf, elf_on_a_shelf, shelf] = self.counts;
+        Ok([elf, elf_on_a_shelf, shelf - elf_on_a_shelf])
+    }
+
+    fn scan(&mut self, last: bool) {
+        // Until the end, hold back the last char so word boundaries can look past a match
+        let limit = match last {
+            true => self.window.len(),
+            false => self.window.char_indices().next_back().map_or(0, |(i, _)| i),
+        };
+
+        for m in self.counter.find(&self.window) {
+            let range = m.start..m.end;
+            if range.end <= self.counted || range.end > limit {
+                continue;
+            }
+            if self.options.word_boundary && !is_whole_word(&self.window, &range) {
+                continue;
+            }
+            self.counts[m.phrase] += 1;
+        }
+
+        // Keep room for a match ending just past `limit` and the char before it
+        let mut cut = limit.saturating_sub(self.longest_phrase + MAX_CHAR_LEN);
+        while !self.window.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        self.window.drain(..cut);
+        self.counted = limit - cut;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::find_elves;
+    use super::*;
+
+    fn stream(text: &str, chunk_size: usize, options: MatchOptions) -> [usize; 3] {
+        let mut elves = ElfStream::new(options);
+        for chunk in text.as_bytes().chunks(chunk_size) {
+            elves.feed(chunk).unwrap();
+        }
+        elves.finish().unwrap()
+    }
+
+    #[test]
+    fn test_matches_whole_body_count() {
+        let text = "Elf on a ＳＨＥＬＦ, an elf on a shelf on a shelf. elfelf shelf—élf elf";
+        for bits in 0..8 {
+            let options = MatchOptions {
+                case_insensitive: bits & 1 != 0,
+                nfkc: bits & 2 != 0,
+                word_boundary: bits & 4 != 0,
+            };
+            let found = find_elves(text, &options);
+            let expected = [
+                found.elf.len(),
+                found.elf_on_a_shelf.len(),
+                found.orphan_shelf.len(),
+            ];
+            for chunk_size in 1..=text.len() {
+                assert_eq!(
+                    stream(text, chunk_size, options),
+                    expected,
+                    "{options:?} in chunks of {chunk_size}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let mut elves = ElfStream::new(MatchOptions::default());
+        assert!(elves.feed(b"elf \xff").is_err());
+
+        let mut elves = ElfStream::new(MatchOptions::default());
+        elves
+            .feed("elf é".as_bytes().split_last().unwrap().1)
+            .unwrap();
+        assert!(elves.finish().is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use rocket::FromForm;
+use std::ops::Range;
+use unicode_normalization::UnicodeNormalization;
+
+#[derive(Clone, Copy, Debug, Default, FromForm)]
+pub struct MatchOptions {
+    // "Elf" and "ELF" count as "elf"
+    pub case_insensitive: bool,
+    // Compatibility forms like "ＥＬＦ" count as their plain equivalents
+    pub nfkc: bool,
+    // Only whole words count, so the elf in "shelf" or "elfin" doesn't
+    pub word_boundary: bool,
+}
+
+// The text as matched, with a way back to where each byte came from in the original
+pub struct Folded {
+    text: String,
+    // Original byte range of the char each folded byte came from
+    origins: Vec<Range<usize>>,
+}
+
+impl Folded {
+    // Normalizes char by char, so combining sequences stay decomposed
+    pub fn new(raw: &str, options: &MatchOptions) -> Folded {
+        let mut folded = Folded {
+            text: String::with_capacity(raw.len()),
+            origins: Vec::with_capacity(raw.len()),
+        };
+
+        for (start, c) in raw.char_indices() {
+            let origin = start..start + c.len_utf8();
+            let mut push = |c: char| match options.case_insensitive {
+                true => folded.text.extend(c.to_lowercase()),
+                false => folded.text.push(c),
+            };
+            match options.nfkc {
+                true => std::iter::once(c).nfkc().for_each(&mut push),
+                false => push(c),
+            }
+            folded.origins.resize(folded.text.len(), origin);
+        }
+
+        folded
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    // Widens to whole original chars when a match starts or ends inside an expansion
+    pub fn original(&self, range: &Range<usize>) -> Range<usize> {
+        self.origins[range.start].start..self.origins[range.end - 1].end
+    }
+}
+
+// Whether the chars either side of `range` in `text` aren't letters or digits
+pub fn is_whole_word(text: &str, range: &Range<usize>) -> bool {
+    let before = text[..range.start].chars().next_back();
+    let after = text[range.end..].chars().next();
+    !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(case_insensitive: bool, nfkc: bool) -> MatchOptions {
+        MatchOptions {
+            case_insensitive,
+            nfkc,
+            word_boundary: false,
+        }
+    }
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(
+            Folded::new("ＥＬＦ Elf", &options(false, false)).text(),
+            "ＥＬＦ Elf"
+        );
+        assert_eq!(
+            Folded::new("ＥＬＦ Elf", &options(true, false)).text(),
+            "ｅｌｆ elf"
+        );
+        assert_eq!(
+            Folded::new("ＥＬＦ Elf", &options(false, true)).text(),
+            "ELF Elf"
+        );
+        assert_eq!(
+            Folded::new("ＥＬＦ Elf", &options(true, true)).text(),
+            "elf elf"
+        );
+    }
+
+    #[test]
+    fn test_original_ranges() {
+        let raw = "ＥＬＦ seﬂ";
+        let folded = Folded::new(raw, &options(true, true));
+        assert_eq!(folded.text(), "elf sefl");
+        assert_eq!(folded.original(&(0..3)), 0..9);
+        assert_eq!(&raw[folded.original(&(5..7))], "eﬂ");
+    }
+
+    #[test]
+    fn test_is_whole_word() {
+        let text = "shelf, elf elfin";
+        assert!(!is_whole_word(text, &(2..5)));
+        assert!(is_whole_word(text, &(7..10)));
+        assert!(!is_whole_word(text, &(11..14)));
+    }
+}
@@ -1,148 +1,715 @@
 use base64::{engine::general_purpose, Engine as _};
-use rocket::http::{CookieJar, Status};
+use rand::RngCore;
+use rocket::http::{Cookie, CookieJar, Status};
+use rocket::response::status::{Created, Custom};
 use rocket::serde::json::{serde_json, Json};
 use rocket::serde::{Deserialize, Serialize};
-use rocket::{get, routes};
+use rocket::{delete, get, post, put, routes, State};
+use shuttle_persist::{PersistError, PersistInstance};
 use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::sync::Mutex;
+
+mod encoding;
+mod pantry;
+mod plan;
+mod shopping;
+mod signing;
+mod units;
 
 pub fn routes() -> Vec<rocket::Route> {
-    routes![bake, decode]
+    routes![
+        bake,
+        bake_stored,
+        decode,
+        issue_recipe,
+        pantry_read,
+        pantry_update,
+        plan_bake,
+        recipe_read,
+        recipe_update,
+        reservation_commit,
+        reservation_read,
+        reservation_release,
+        reserve,
+        shopping_list
+    ]
 }
 
-#[get("/decode")]
-fn decode(cookies: &CookieJar<'_>) -> Result<String, Status> {
-    match cookies.get("recipe") {
-        Some(cookie) => {
-            let recipe = cookie.value();
+// Read from Rocket's config, e.g. `ROCKET_STRICT_RECIPES=true`
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct RecipeConfig {
+    // Key for signing recipe cookies; without one, cookies only last until restart
+    #[serde(default = "random_secret")]
+    recipe_secret: String,
+    // Reject recipes that `POST /7/recipe` didn't sign, rather than trusting them
+    #[serde(default)]
+    strict_recipes: bool,
+}
 
-            let decoded = &general_purpose::STANDARD.decode(recipe);
+fn random_secret() -> String {
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    hex::encode(secret)
+}
 
-            match decoded {
-                Ok(decoded) => match String::from_utf8(decoded.clone()) {
-                    Ok(decoded) => Ok(decoded),
-                    Err(e) => {
-                        println!("Failed to create UTF8 string from recipe bytes {recipe}: {e:?}");
-                        Err(Status::BadRequest)
-                    }
-                },
-                Err(e) => {
-                    println!("Failed to base64 decode recipe {recipe}: {e:?}");
-                    Err(Status::BadRequest)
-                }
-            }
-        }
-        None => Err(Status::BadRequest),
-    }
+// Checks the signature the same way /7/bake does, so cookies from /7/recipe decode too
+#[get("/decode")]
+fn decode(cookies: &CookieJar<'_>, config: &State<RecipeConfig>) -> Result<String, Custom<String>> {
+    recipe_from_cookie(cookies, config)
 }
 
 #[derive(Deserialize, Serialize)]
 struct BakeRequest {
-    recipe: HashMap<String, usize>,
-    pantry: HashMap<String, usize>,
+    recipe: HashMap<String, units::Quantity>,
+    pantry: HashMap<String, units::Quantity>,
+    // Grams per millilitre, on top of the built in ones
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    densities: HashMap<String, f64>,
 }
 #[derive(Debug, Deserialize, Serialize)]
 struct BakeResponse {
     cookies: usize,
     pantry: HashMap<String, usize>,
 }
+// The pantry comes back in whatever units it was given in
+#[derive(Debug, Deserialize, Serialize)]
+struct UnitBakeResponse {
+    cookies: usize,
+    pantry: HashMap<String, units::Quantity>,
+}
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum BakeResult {
+    Plain(BakeResponse),
+    Units(UnitBakeResponse),
+}
+
+fn bare(quantities: &HashMap<String, units::Quantity>) -> Option<HashMap<String, usize>> {
+    quantities
+        .iter()
+        .map(|(ingredient, quantity)| match quantity {
+            units::Quantity::Bare(amount) => Some((ingredient.clone(), *amount)),
+            units::Quantity::Measured { .. } => None,
+        })
+        .collect()
+}
+// Signs the recipe and hands it back as the `recipe` cookie
+#[post("/recipe", data = "<recipe>")]
+fn issue_recipe(
+    recipe: Json<BakeRequest>,
+    cookies: &CookieJar<'_>,
+    config: &State<RecipeConfig>,
+) -> Result<String, Status> {
+    let json = serde_json::to_string(&recipe.into_inner()).map_err(|e| {
+        println!("Failed to serialize recipe: {e}");
+        Status::InternalServerError
+    })?;
+    let payload = general_purpose::STANDARD.encode(json);
+    let value = signing::sign(&payload, config.recipe_secret.as_bytes());
+
+    cookies.add(Cookie::new("recipe", value.clone()));
+    Ok(value)
+}
+
 #[get("/bake")]
-fn bake(cookies: &CookieJar<'_>) -> Result<Json<BakeResponse>, Status> {
-    let cookie_string = recipe_from_cookie(cookies)?;
+fn bake(
+    cookies: &CookieJar<'_>,
+    config: &State<RecipeConfig>,
+) -> Result<Json<BakeResult>, Custom<String>> {
+    let cookie_string = recipe_from_cookie(cookies, config)?;
 
     match serde_json::from_str::<BakeRequest>(&cookie_string) {
         Ok(recipe) => {
-            let result = calc_baked_cookies(recipe.recipe, recipe.pantry);
+            // Recipes without units keep the exact integer bake
+            let result = match (bare(&recipe.recipe), bare(&recipe.pantry)) {
+                (Some(needed), Some(pantry)) => {
+                    BakeResult::Plain(calc_baked_cookies(needed, pantry))
+                }
+                _ => {
+                    let (cookies, pantry) =
+                        units::bake(&recipe.recipe, &recipe.pantry, &recipe.densities).map_err(
+                            |e| {
+                                println!("Failed to bake {cookie_string}: {e}");
+                                Custom(Status::BadRequest, e)
+                            },
+                        )?;
+                    BakeResult::Units(UnitBakeResponse { cookies, pantry })
+                }
+            };
             println!("@bake {cookie_string} => {result:?}");
             Ok(Json(result))
         }
         Err(e) => {
             println!("Failed to deserialize recipe {cookie_string}: {e:?}");
-            Err(Status::BadRequest)
+            Err(Custom(Status::BadRequest, format!("Invalid recipe: {e}")))
         }
     }
 }
 
-fn calc_baked_cookies(
-    recipe: HashMap<String, usize>,
-    mut pantry: HashMap<String, usize>,
-) -> BakeResponse {
-    let mut cookies = 0;
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct PlanRecipe {
+    value: f64,
+    ingredients: HashMap<String, usize>,
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct PlanRequest {
+    recipes: HashMap<String, PlanRecipe>,
+    pantry: HashMap<String, usize>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct PlanResponse {
+    cookies: HashMap<String, usize>,
+    value: f64,
+    pantry: HashMap<String, usize>,
+    // False if the planner gave up before proving no better plan exists
+    optimal: bool,
+}
+
+// How many of each recipe to bake from one pantry for the most total value
+#[post("/plan", data = "<request>")]
+fn plan_bake(request: Json<PlanRequest>) -> Result<Json<PlanResponse>, Status> {
+    let PlanRequest { recipes, pantry } = request.into_inner();
 
-    // Bake until you can't bake no more
-    loop {
-        let mut can_bake = true;
+    let mut names: Vec<&String> = recipes.keys().collect();
+    names.sort();
+    let mut ingredients: Vec<&String> = recipes
+        .values()
+        .flat_map(|r| r.ingredients.iter())
+        .filter(|(_, &amount)| amount > 0)
+        .map(|(ingredient, _)| ingredient)
+        .collect();
+    ingredients.sort();
+    ingredients.dedup();
 
-        for (ingredient, amount_needed) in &recipe {
-            if amount_needed == &0 {
-                continue;
+    if names.len() > plan::MAX_RECIPES || ingredients.len() > plan::MAX_INGREDIENTS {
+        println!(
+            "Plan has {} recipes and {} ingredients, at most {} and {} are allowed",
+            names.len(),
+            ingredients.len(),
+            plan::MAX_RECIPES,
+            plan::MAX_INGREDIENTS
+        );
+        return Err(Status::BadRequest);
+    }
+
+    for name in &names {
+        let recipe = &recipes[*name];
+        if !recipe.value.is_finite() || recipe.value.abs() > plan::MAX_VALUE {
+            println!("Recipe {name} has invalid value {}", recipe.value);
+            return Err(Status::BadRequest);
+        }
+        // Free cookies with a positive value would make the plan infinite
+        if recipe.value > 0.0 && recipe.ingredients.values().all(|&amount| amount == 0) {
+            println!("Recipe {name} needs no ingredients");
+            return Err(Status::BadRequest);
+        }
+    }
+
+    let problem = plan::Problem {
+        values: names.iter().map(|name| recipes[*name].value).collect(),
+        needs: names
+            .iter()
+            .map(|name| {
+                let recipe = &recipes[*name];
+                ingredients
+                    .iter()
+                    .map(|i| recipe.ingredients.get(*i).copied().unwrap_or(0) as u64)
+                    .collect()
+            })
+            .collect(),
+        supply: ingredients
+            .iter()
+            .map(|i| pantry.get(*i).copied().unwrap_or(0) as u64)
+            .collect(),
+    };
+    let solution = problem.solve();
+
+    let mut leftover = pantry.clone();
+    for (name, &count) in names.iter().zip(&solution.counts) {
+        for (ingredient, &amount) in &recipes[*name].ingredients {
+            if let Some(left) = leftover.get_mut(ingredient) {
+                *left -= amount * count as usize;
             }
+        }
+    }
 
-            match pantry.get(ingredient) {
-                Some(amount) => {
-                    if *amount < *amount_needed {
-                        can_bake = false;
-                        break;
-                    }
+    let result = PlanResponse {
+        cookies: names
+            .iter()
+            .map(|name| name.to_string())
+            .zip(solution.counts.iter().map(|&count| count as usize))
+            .collect(),
+        value: solution.value,
+        pantry: leftover,
+        optimal: solution.optimal,
+    };
+    println!("@plan_bake => {result:?}");
+    Ok(Json(result))
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct ShoppingRequest {
+    recipe: HashMap<String, usize>,
+    pantry: HashMap<String, usize>,
+    // Packs each ingredient is sold in
+    #[serde(default)]
+    prices: HashMap<String, Vec<shopping::Pack>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct ShoppingList {
+    cookies: usize,
+    missing: HashMap<String, usize>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    purchases: HashMap<String, shopping::Purchase>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    total_cost: Option<f64>,
+    // Missing ingredients the price table doesn't sell
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    unpriced: Vec<String>,
+}
+
+// What to buy to bake `cookies` cookies, ignoring ingredients the recipe needs none of
+#[post("/shopping-list?<cookies>", data = "<request>")]
+fn shopping_list(
+    cookies: Option<&str>,
+    request: Json<ShoppingRequest>,
+) -> Result<Json<ShoppingList>, Status> {
+    let cookies = match cookies.map(str::parse::<usize>) {
+        Some(Ok(cookies)) => cookies,
+        _ => {
+            println!("Invalid cookie count {cookies:?}");
+            return Err(Status::BadRequest);
+        }
+    };
+
+    let mut missing = HashMap::new();
+    for (ingredient, &amount) in request.recipe.iter().filter(|(_, &a)| a > 0) {
+        let needed = amount.checked_mul(cookies).ok_or_else(|| {
+            println!("{cookies} cookies need more {ingredient} than can be counted");
+            Status::BadRequest
+        })?;
+        let have = request.pantry.get(ingredient).copied().unwrap_or(0);
+        if needed > have {
+            missing.insert(ingredient.clone(), needed - have);
+        }
+    }
+
+    let mut list = ShoppingList {
+        cookies,
+        missing,
+        purchases: HashMap::new(),
+        total_cost: None,
+        unpriced: vec![],
+    };
+    if !request.prices.is_empty() {
+        for (ingredient, &amount) in &list.missing {
+            let packs = request.prices.get(ingredient).map_or(&[][..], |p| p);
+            let purchase = shopping::cheapest(packs, amount as u64).map_err(|e| {
+                println!("Can't price {ingredient}: {e}");
+                Status::BadRequest
+            })?;
+            match purchase {
+                Some(purchase) => {
+                    list.purchases.insert(ingredient.clone(), purchase);
                 }
-                None => can_bake = false,
+                None => list.unpriced.push(ingredient.clone()),
             }
         }
+        list.unpriced.sort();
+        list.total_cost = Some(list.purchases.values().map(|p| p.cost).sum());
+    }
 
-        if !can_bake {
-            break;
+    println!("@shopping_list => {list:?}");
+    Ok(Json(list))
+}
+
+pub struct Day7State {
+    pub persist: PersistInstance,
+    // Serializes the load-modify-save cycle so concurrent bakes can't spend the same flour
+    bakery_lock: Mutex<()>,
+}
+
+impl Day7State {
+    pub fn new(persist: PersistInstance) -> Self {
+        Day7State {
+            persist,
+            bakery_lock: Mutex::new(()),
         }
+    }
+}
 
-        // Remove ingredients from pantry
-        cookies += 1;
-        for (ingredient, amount_needed) in &recipe {
-            if amount_needed == &0 {
-                continue;
-            }
+const BAKERY_KEY: &str = "day7_bakery";
+const DEFAULT_HOLD_SECONDS: i64 = 300;
+const MAX_HOLD_SECONDS: i64 = 3600;
+
+// Loads the bakery with expired reservations already released
+fn load_bakery(persist: &PersistInstance) -> Result<pantry::Bakery, Status> {
+    let mut bakery: pantry::Bakery = match persist.load::<String>(BAKERY_KEY) {
+        Ok(json) => serde_json::from_str(&json).map_err(|e| {
+            println!("Failed to parse stored bakery: {e}");
+            Status::InternalServerError
+        })?,
+        // Only a bakery that was never saved starts empty; saving over one we failed to read
+        // would lose the stock
+        Err(PersistError::Open(e)) if e.kind() == ErrorKind::NotFound => pantry::Bakery::default(),
+        Err(e) => {
+            println!("Error loading bakery: {e}");
+            return Err(Status::InternalServerError);
+        }
+    };
+    bakery.expire(chrono::Utc::now().timestamp());
+    Ok(bakery)
+}
 
-            *pantry.get_mut(ingredient).unwrap() -= *amount_needed;
+fn save_bakery(persist: &PersistInstance, bakery: &pantry::Bakery) -> Result<(), Status> {
+    let json = serde_json::to_string(bakery).map_err(|e| {
+        println!("Failed to serialize bakery: {e}");
+        Status::InternalServerError
+    })?;
+    persist.save(BAKERY_KEY, json).map_err(|e| {
+        println!("Error saving bakery: {e}");
+        Status::InternalServerError
+    })
+}
+
+fn bakery_status(e: pantry::BakeryError) -> Status {
+    println!("Bakery refused: {e}");
+    match e {
+        pantry::BakeryError::UnknownRecipe(_) | pantry::BakeryError::UnknownReservation(_) => {
+            Status::NotFound
         }
+        pantry::BakeryError::Insufficient { .. } => Status::Conflict,
+        pantry::BakeryError::TooMany => Status::BadRequest,
     }
+}
 
-    BakeResponse {
+#[get("/pantry")]
+fn pantry_read(state: &State<Day7State>) -> Result<Json<HashMap<String, usize>>, Status> {
+    let bakery = load_bakery(&state.persist)?;
+    Ok(Json(bakery.stock))
+}
+
+// Replaces the stored pantry; 409 if it no longer covers the active reservations
+#[put("/pantry", data = "<stock>")]
+fn pantry_update(
+    stock: Json<HashMap<String, usize>>,
+    state: &State<Day7State>,
+) -> Result<Json<HashMap<String, usize>>, Status> {
+    let _lock = state.bakery_lock.lock().unwrap();
+    let mut bakery = load_bakery(&state.persist)?;
+    bakery.restock(stock.into_inner()).map_err(bakery_status)?;
+    save_bakery(&state.persist, &bakery)?;
+    Ok(Json(bakery.stock))
+}
+
+#[get("/recipes/<name>")]
+fn recipe_read(
+    name: &str,
+    state: &State<Day7State>,
+) -> Result<Json<HashMap<String, usize>>, Status> {
+    let mut bakery = load_bakery(&state.persist)?;
+    bakery
+        .recipes
+        .remove(name)
+        .map(Json)
+        .ok_or(Status::NotFound)
+}
+
+#[put("/recipes/<name>", data = "<recipe>")]
+fn recipe_update(
+    name: &str,
+    recipe: Json<HashMap<String, usize>>,
+    state: &State<Day7State>,
+) -> Result<Json<HashMap<String, usize>>, Status> {
+    let _lock = state.bakery_lock.lock().unwrap();
+    let mut bakery = load_bakery(&state.persist)?;
+    bakery
+        .recipes
+        .insert(name.to_string(), recipe.clone().into_inner());
+    save_bakery(&state.persist, &bakery)?;
+    Ok(recipe)
+}
+
+// Bakes a stored recipe from the unreserved pantry: exactly `cookies` of them or 409, or as
+// many as possible when `cookies` is left out
+#[post("/bake/<recipe>?<cookies>")]
+fn bake_stored(
+    recipe: &str,
+    cookies: Option<&str>,
+    state: &State<Day7State>,
+) -> Result<Json<BakeResponse>, Status> {
+    // Parsed by hand so a bad count is a 400 rather than a bake of everything
+    let cookies = match cookies.map(str::parse::<usize>) {
+        None => None,
+        Some(Ok(cookies)) => Some(cookies),
+        Some(Err(_)) => {
+            println!("Invalid cookie count {cookies:?}");
+            return Err(Status::BadRequest);
+        }
+    };
+
+    let _lock = state.bakery_lock.lock().unwrap();
+    let mut bakery = load_bakery(&state.persist)?;
+    let cookies = bakery.bake(recipe, cookies).map_err(bakery_status)?;
+    save_bakery(&state.persist, &bakery)?;
+
+    let response = BakeResponse {
         cookies,
-        pantry: pantry,
+        pantry: bakery.stock,
+    };
+    println!("@bake/{recipe} => {response:?}");
+    Ok(Json(response))
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct ReservationRequest {
+    recipe: String,
+    cookies: usize,
+    seconds: Option<i64>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct ReservationResponse {
+    id: String,
+    #[serde(flatten)]
+    reservation: pantry::Reservation,
+}
+
+// Holds the ingredients for a bake for `seconds` (default five minutes, at most an hour)
+#[post("/reservations", data = "<request>")]
+fn reserve(
+    request: Json<ReservationRequest>,
+    state: &State<Day7State>,
+) -> Result<Created<Json<ReservationResponse>>, Status> {
+    let seconds = request.seconds.unwrap_or(DEFAULT_HOLD_SECONDS);
+    if !(1..=MAX_HOLD_SECONDS).contains(&seconds) {
+        println!("Can't hold ingredients for {seconds}s");
+        return Err(Status::BadRequest);
     }
+
+    let _lock = state.bakery_lock.lock().unwrap();
+    let mut bakery = load_bakery(&state.persist)?;
+    let id = ulid::Ulid::new().to_string();
+    let expires_at = chrono::Utc::now().timestamp() + seconds;
+    let reservation = bakery
+        .reserve(id.clone(), &request.recipe, request.cookies, expires_at)
+        .map_err(bakery_status)?
+        .clone();
+    save_bakery(&state.persist, &bakery)?;
+
+    let location = format!("/7/reservations/{id}");
+    Ok(Created::new(location).body(Json(ReservationResponse { id, reservation })))
 }
 
-fn recipe_from_cookie(cookies: &CookieJar<'_>) -> Result<String, Status> {
-    match cookies.get("recipe") {
-        Some(cookie) => {
-            let recipe = cookie.value();
+#[get("/reservations/<id>")]
+fn reservation_read(
+    id: &str,
+    state: &State<Day7State>,
+) -> Result<Json<ReservationResponse>, Status> {
+    let mut bakery = load_bakery(&state.persist)?;
+    match bakery.reservations.remove(id) {
+        Some(reservation) => Ok(Json(ReservationResponse {
+            id: id.to_string(),
+            reservation,
+        })),
+        None => Err(Status::NotFound),
+    }
+}
 
-            let decoded = &general_purpose::STANDARD.decode(recipe);
+// Bakes what was reserved; 404 once the reservation has expired
+#[post("/reservations/<id>/commit")]
+fn reservation_commit(id: &str, state: &State<Day7State>) -> Result<Json<BakeResponse>, Status> {
+    let _lock = state.bakery_lock.lock().unwrap();
+    let mut bakery = load_bakery(&state.persist)?;
+    let reservation = bakery.commit(id).map_err(bakery_status)?;
+    save_bakery(&state.persist, &bakery)?;
 
-            match decoded {
-                Ok(decoded) => match String::from_utf8(decoded.clone()) {
-                    Ok(decoded) => Ok(decoded),
-                    Err(e) => {
-                        println!("Failed to create UTF8 string from recipe bytes {recipe}: {e:?}");
-                        Err(Status::BadRequest)
-                    }
-                },
-                Err(e) => {
-                    println!("Failed to base64 decode recipe {recipe}: {e:?}");
-                    Err(Status::BadRequest)
+    Ok(Json(BakeResponse {
+        cookies: reservation.cookies,
+        pantry: bakery.stock,
+    }))
+}
+
+#[delete("/reservations/<id>")]
+fn reservation_release(id: &str, state: &State<Day7State>) -> Result<(), Status> {
+    let _lock = state.bakery_lock.lock().unwrap();
+    let mut bakery = load_bakery(&state.persist)?;
+    bakery.release(id).map_err(bakery_status)?;
+    save_bakery(&state.persist, &bakery)
+}
+
+// As many cookies as the scarcest ingredient allows; ingredients needing 0 are ignored, and a
+// recipe that needs nothing at all bakes nothing rather than infinitely many
+fn calc_baked_cookies(
+    recipe: HashMap<String, usize>,
+    mut pantry: HashMap<String, usize>,
+) -> BakeResponse {
+    let cookies = recipe
+        .iter()
+        .filter(|(_, &needed)| needed > 0)
+        .map(|(ingredient, &needed)| pantry.get(ingredient).copied().unwrap_or(0) / needed)
+        .min()
+        .unwrap_or(0);
+
+    for (ingredient, &needed) in &recipe {
+        if let Some(amount) = pantry.get_mut(ingredient) {
+            // cookies * needed <= amount by construction, so neither step can overflow
+            *amount -= cookies * needed;
+        }
+    }
+
+    BakeResponse { cookies, pantry }
+}
+
+// Unsigned recipes are 401 in strict mode, and bad signatures are always 403; the error body
+// says what was wrong, down to which decoding stage failed
+fn recipe_from_cookie(
+    cookies: &CookieJar<'_>,
+    config: &RecipeConfig,
+) -> Result<String, Custom<String>> {
+    match cookies.get("recipe") {
+        Some(cookie) => {
+            let verified = signing::verify(cookie.value(), config.recipe_secret.as_bytes())
+                .map_err(|e| {
+                    println!("Rejecting recipe: {e}");
+                    Custom(Status::Forbidden, e)
+                })?;
+            let recipe = match verified {
+                signing::Verified::Signed(payload) => payload,
+                signing::Verified::Unsigned(_) if config.strict_recipes => {
+                    println!("Rejecting unsigned recipe {}", cookie.value());
+                    return Err(Custom(
+                        Status::Unauthorized,
+                        "Recipe is not signed".to_string(),
+                    ));
                 }
-            }
+                signing::Verified::Unsigned(payload) => payload,
+            };
+
+            encoding::decode(recipe).map_err(|e| {
+                println!("Failed to decode recipe {recipe}: {e}");
+                Custom(e.status(), e.to_string())
+            })
         }
-        None => Err(Status::BadRequest),
+        None => Err(Custom(Status::BadRequest, "No recipe cookie".to_string())),
     }
 }
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::prelude::*;
     use rocket::http::Cookie;
     use rocket::http::Status;
     use rocket::local::blocking::Client;
 
+    // The original one-cookie-at-a-time bake, kept to check the closed form against
+    fn calc_baked_cookies_loop(
+        recipe: HashMap<String, usize>,
+        mut pantry: HashMap<String, usize>,
+    ) -> BakeResponse {
+        let mut cookies = 0;
+
+        // Bake until you can't bake no more
+        loop {
+            let mut can_bake = true;
+
+            for (ingredient, amount_needed) in &recipe {
+                if amount_needed == &0 {
+                    continue;
+                }
+
+                match pantry.get(ingredient) {
+                    Some(amount) => {
+                        if *amount < *amount_needed {
+                            can_bake = false;
+                            break;
+                        }
+                    }
+                    None => can_bake = false,
+                }
+            }
+
+            if !can_bake {
+                break;
+            }
+
+            // Remove ingredients from pantry
+            cookies += 1;
+            for (ingredient, amount_needed) in &recipe {
+                if amount_needed == &0 {
+                    continue;
+                }
+
+                *pantry.get_mut(ingredient).unwrap() -= *amount_needed;
+            }
+        }
+
+        BakeResponse { cookies, pantry }
+    }
+
+    // Small amounts over a handful of names, so recipes and pantries overlap only partly
+    fn ingredients(max: usize) -> impl Strategy<Value = HashMap<String, usize>> {
+        prop::collection::hash_map("[a-e]", 0..=max, 0..5)
+    }
+
+    proptest! {
+        #[test]
+        fn test_closed_form_matches_loop(recipe in ingredients(10), pantry in ingredients(1000)) {
+            // The loop never ends for a recipe that needs nothing
+            prop_assume!(recipe.values().any(|&needed| needed > 0));
+
+            let expected = calc_baked_cookies_loop(recipe.clone(), pantry.clone());
+            let actual = calc_baked_cookies(recipe, pantry);
+            prop_assert_eq!(actual.cookies, expected.cookies);
+            prop_assert_eq!(actual.pantry, expected.pantry);
+        }
+    }
+
+    #[test]
+    fn test_bake_huge_pantry() {
+        let recipe = HashMap::from([("flour".to_string(), 3), ("sugar".to_string(), 0)]);
+        let pantry = HashMap::from([("flour".to_string(), usize::MAX), ("sugar".to_string(), 1)]);
+
+        let baked = calc_baked_cookies(recipe, pantry);
+        assert_eq!(baked.cookies, usize::MAX / 3);
+        assert_eq!(baked.pantry["flour"], usize::MAX % 3);
+        assert_eq!(baked.pantry["sugar"], 1);
+    }
+
+    #[test]
+    fn test_bake_nothing_needed() {
+        let recipe = HashMap::from([("flour".to_string(), 0)]);
+        let pantry = HashMap::from([("flour".to_string(), 10)]);
+
+        let baked = calc_baked_cookies(recipe, pantry);
+        assert_eq!(baked.cookies, 0);
+        assert_eq!(baked.pantry["flour"], 10);
+    }
+
+    // Legacy mode, so the unsigned cookies these tests send are still accepted
+    fn decode_rocket() -> rocket::Rocket<rocket::Build> {
+        let config = RecipeConfig {
+            recipe_secret: "north pole".to_string(),
+            strict_recipes: false,
+        };
+        rocket::build().mount("/", routes![decode]).manage(config)
+    }
+
     #[test]
     fn test_decode_success() {
-        let rocket = rocket::build().mount("/", routes![decode]);
+        let rocket = decode_rocket();
         let client = Client::tracked(rocket).expect("valid rocket instance");
         let cookie = Cookie::new("recipe", "eyJmbG91ciI6MTAwLCJjaG9jb2xhdGUgY2hpcHMiOjIwfQ==");
         let response = client.get("/decode").cookie(cookie).dispatch();
@@ -155,7 +722,7 @@ mod test {
 
     #[test]
     fn test_decode_invalid_base64() {
-        let rocket = rocket::build().mount("/", routes![decode]);
+        let rocket = decode_rocket();
         let client = Client::tracked(rocket).expect("valid rocket instance");
         let cookie = Cookie::new("recipe", "invalid base64");
         let response = client.get("/decode").cookie(cookie).dispatch();
@@ -164,7 +731,7 @@ mod test {
 
     #[test]
     fn test_decode_invalid_utf8() {
-        let rocket = rocket::build().mount("/", routes![decode]);
+        let rocket = decode_rocket();
         let client = Client::tracked(rocket).expect("valid rocket instance");
         let invalid_utf8 = [0, 159, 146, 150]; // Invalid UTF-8 bytes
         let cookie = Cookie::new("recipe", general_purpose::STANDARD.encode(invalid_utf8));
@@ -174,9 +741,501 @@ mod test {
 
     #[test]
     fn test_decode_missing_cookie() {
-        let rocket = rocket::build().mount("/", routes![decode]);
+        let rocket = decode_rocket();
         let client = Client::tracked(rocket).expect("valid rocket instance");
         let response = client.get("/decode").dispatch();
         assert_eq!(response.status(), Status::BadRequest);
     }
+
+    #[test]
+    fn test_decode_compressed() {
+        use flate2::{write::GzEncoder, Compression};
+        use std::io::Write;
+
+        let rocket = decode_rocket();
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(RECIPE.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+        let cookie = Cookie::new("recipe", general_purpose::URL_SAFE_NO_PAD.encode(&gzipped));
+        let response = client.get("/decode").cookie(cookie).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.into_string().unwrap(), RECIPE);
+
+        let cookie = Cookie::new("recipe", general_purpose::STANDARD.encode(&gzipped[..20]));
+        let response = client.get("/decode").cookie(cookie).dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+        assert!(response
+            .into_string()
+            .unwrap()
+            .starts_with("decompression failed"));
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(&vec![b' '; 1 << 20]).unwrap();
+        let bomb = general_purpose::STANDARD.encode(encoder.finish().unwrap());
+        let response = client
+            .get("/decode")
+            .cookie(Cookie::new("recipe", bomb))
+            .dispatch();
+        assert_eq!(response.status(), Status::PayloadTooLarge);
+    }
+
+    fn recipe_client(strict_recipes: bool) -> Client {
+        let config = RecipeConfig {
+            recipe_secret: "north pole".to_string(),
+            strict_recipes,
+        };
+        let rocket = rocket::build()
+            .mount("/", routes())
+            .manage(config)
+            .manage(bakery_state());
+        Client::tracked(rocket).expect("valid rocket instance")
+    }
+
+    fn bakery_state() -> Day7State {
+        let dir = std::env::temp_dir().join(format!("day7-bakery-{}", ulid::Ulid::new()));
+        Day7State::new(PersistInstance::new(dir).unwrap())
+    }
+
+    // A pantry for 10 batches of shortbread
+    fn stock_bakery(client: &Client) {
+        let response = client
+            .put("/pantry")
+            .body(r#"{"flour":1000,"butter":500}"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let response = client
+            .put("/recipes/shortbread")
+            .body(r#"{"flour":100,"butter":50}"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    const RECIPE: &str = r#"{"recipe":{"flour":95},"pantry":{"flour":385}}"#;
+
+    #[test]
+    fn test_signed_recipe() {
+        let client = recipe_client(true);
+        let response = client.post("/recipe").body(RECIPE).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert!(response.cookies().get("recipe").is_some());
+
+        // The tracked client sends the issued cookie back
+        let response = client.get("/bake").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let baked: BakeResponse = response.into_json().unwrap();
+        assert_eq!(baked.cookies, 4);
+        assert_eq!(baked.pantry.get("flour").unwrap(), &5);
+
+        let response = client.get("/decode").dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let decoded: BakeRequest = response.into_json().unwrap();
+        assert_eq!(
+            bare(&decoded.recipe),
+            Some(HashMap::from([("flour".to_string(), 95)]))
+        );
+
+        let response = client
+            .get("/decode")
+            .cookie(Cookie::new(
+                "recipe",
+                general_purpose::STANDARD.encode(RECIPE),
+            ))
+            .dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+    }
+
+    #[test]
+    fn test_tampered_recipe() {
+        let client = recipe_client(false);
+        let signed = client
+            .post("/recipe")
+            .body(RECIPE)
+            .dispatch()
+            .into_string()
+            .unwrap();
+        let (_, signature) = signed.split_once('.').unwrap();
+        let greedy =
+            general_purpose::STANDARD.encode(r#"{"recipe":{"flour":1},"pantry":{"flour":385}}"#);
+
+        let cookie = Cookie::new("recipe", format!("{greedy}.{signature}"));
+        let response = client.get("/bake").cookie(cookie).dispatch();
+        assert_eq!(response.status(), Status::Forbidden);
+    }
+
+    #[test]
+    fn test_unsigned_recipe() {
+        let unsigned = general_purpose::STANDARD.encode(RECIPE);
+
+        let client = recipe_client(true);
+        let cookie = Cookie::new("recipe", unsigned.clone());
+        let response = client.get("/bake").cookie(cookie).dispatch();
+        assert_eq!(response.status(), Status::Unauthorized);
+
+        let client = recipe_client(false);
+        let cookie = Cookie::new("recipe", unsigned);
+        let response = client.get("/bake").cookie(cookie).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+    }
+
+    #[test]
+    fn test_plan() {
+        let rocket = rocket::build().mount("/", routes![plan_bake]);
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+
+        let body = r#"{
+            "recipes": {
+                "chocolate chip": {"value": 7, "ingredients": {"flour": 6, "chocolate chips": 1}},
+                "shortbread": {"value": 5, "ingredients": {"flour": 5, "butter": 2, "sugar": 0}},
+                "free": {"value": 0, "ingredients": {}}
+            },
+            "pantry": {"flour": 10, "butter": 9, "chocolate chips": 3, "milk": 4}
+        }"#;
+        let response = client.post("/plan").body(body).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let plan: PlanResponse = response.into_json().unwrap();
+
+        assert_eq!(plan.cookies["shortbread"], 2);
+        assert_eq!(plan.cookies["chocolate chip"], 0);
+        assert_eq!(plan.cookies["free"], 0);
+        assert_eq!(plan.value, 10.0);
+        assert!(plan.optimal);
+        assert_eq!(
+            plan.pantry,
+            HashMap::from([
+                ("flour".to_string(), 0),
+                ("butter".to_string(), 5),
+                ("chocolate chips".to_string(), 3),
+                ("milk".to_string(), 4),
+            ])
+        );
+
+        let body =
+            r#"{"recipes": {"air": {"value": 1, "ingredients": {"flour": 0}}}, "pantry": {}}"#;
+        let response = client.post("/plan").body(body).dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let body =
+            r#"{"recipes": {"gold": {"value": 1e300, "ingredients": {"flour": 1}}}, "pantry": {}}"#;
+        let response = client.post("/plan").body(body).dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let recipes: Vec<String> = (0..=plan::MAX_RECIPES)
+            .map(|r| format!(r#""r{r}": {{"value": 1, "ingredients": {{"flour": 1}}}}"#))
+            .collect();
+        let body = format!(
+            r#"{{"recipes": {{{}}}, "pantry": {{}}}}"#,
+            recipes.join(",")
+        );
+        let response = client.post("/plan").body(body).dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+
+        let ingredients: Vec<String> = (0..=plan::MAX_INGREDIENTS)
+            .map(|i| format!(r#""i{i}": 1"#))
+            .collect();
+        let body = format!(
+            r#"{{"recipes": {{"r": {{"value": 1, "ingredients": {{{}}}}}}}, "pantry": {{}}}}"#,
+            ingredients.join(",")
+        );
+        let response = client.post("/plan").body(body).dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn test_shopping_list() {
+        let rocket = rocket::build().mount("/", routes![shopping_list]);
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+        let body = r#"{
+            "recipe": {"flour": 95, "sugar": 50, "butter": 30, "milk": 0},
+            "pantry": {"flour": 300, "sugar": 500}
+        }"#;
+
+        let response = client
+            .post("/shopping-list?cookies=5")
+            .body(body)
+            .dispatch();
+        let list: ShoppingList = response.into_json().unwrap();
+        assert_eq!(
+            list.missing,
+            HashMap::from([("flour".to_string(), 175), ("butter".to_string(), 150)])
+        );
+        assert_eq!(list.total_cost, None);
+
+        let response = client
+            .post("/shopping-list?cookies=-1")
+            .body(body)
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+        let response = client.post("/shopping-list").body(body).dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn test_shopping_list_prices() {
+        let rocket = rocket::build().mount("/", routes![shopping_list]);
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+        let body = r#"{
+            "recipe": {"flour": 95, "butter": 30, "eggs": 1},
+            "pantry": {"flour": 300},
+            "prices": {
+                "flour": [{"size": 1000, "price": 4.0}, {"size": 250, "price": 1.5}],
+                "butter": [{"size": 250, "price": 2.25}]
+            }
+        }"#;
+
+        let response = client
+            .post("/shopping-list?cookies=13")
+            .body(body)
+            .dispatch();
+        let list: ShoppingList = response.into_json().unwrap();
+        assert_eq!(list.missing["flour"], 935);
+        assert_eq!(list.purchases["flour"].quantity, 1000);
+        assert_eq!(list.purchases["butter"].quantity, 500);
+        assert_eq!(list.total_cost, Some(8.5));
+        assert_eq!(list.unpriced, vec!["eggs"]);
+    }
+
+    #[test]
+    fn test_bake_url_safe_legacy_cookie() {
+        let client = recipe_client(false);
+        let recipe = r#"{"recipe":{"flour???":95},"pantry":{"flour???":385}}"#;
+        let encoded = general_purpose::URL_SAFE_NO_PAD.encode(recipe);
+        assert!(encoded.contains('_'));
+
+        let response = client
+            .get("/bake")
+            .cookie(Cookie::new("recipe", encoded))
+            .dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let baked: BakeResponse = response.into_json().unwrap();
+        assert_eq!(baked.cookies, 4);
+
+        let cookie = Cookie::new("recipe", "not base64!");
+        let response = client.get("/bake").cookie(cookie).dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+        assert!(response
+            .into_string()
+            .unwrap()
+            .starts_with("base64 decoding failed"));
+    }
+
+    #[test]
+    fn test_bake_with_units() {
+        let client = recipe_client(false);
+        let recipe = r#"{
+            "recipe": {"flour": {"amount": 1, "unit": "cup"}, "eggs": 1},
+            "pantry": {"flour": {"amount": 0.5, "unit": "kg"}, "eggs": 12},
+            "densities": {"flour": 0.5}
+        }"#;
+        let cookie = Cookie::new("recipe", general_purpose::STANDARD.encode(recipe));
+
+        let response = client.get("/bake").cookie(cookie).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        let baked: UnitBakeResponse = response.into_json().unwrap();
+        assert_eq!(baked.cookies, 4);
+        assert_eq!(
+            baked.pantry["flour"],
+            units::Quantity::Measured {
+                amount: 0.026824,
+                unit: "kg".to_string()
+            }
+        );
+        assert_eq!(baked.pantry["eggs"], units::Quantity::Bare(8));
+
+        let recipe = r#"{"recipe": {"eggs": {"amount": 1, "unit": "g"}}, "pantry": {"eggs": 12}}"#;
+        let cookie = Cookie::new("recipe", general_purpose::STANDARD.encode(recipe));
+        let response = client.get("/bake").cookie(cookie).dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn test_stored_pantry() {
+        let client = recipe_client(false);
+        assert_eq!(
+            client.get("/pantry").dispatch().into_string().unwrap(),
+            "{}"
+        );
+        assert_eq!(
+            client.post("/bake/shortbread").dispatch().status(),
+            Status::NotFound
+        );
+        stock_bakery(&client);
+
+        let response = client.post("/bake/shortbread?cookies=3").dispatch();
+        let baked: BakeResponse = response.into_json().unwrap();
+        assert_eq!(baked.cookies, 3);
+        assert_eq!(baked.pantry["flour"], 700);
+
+        let response = client.post("/bake/shortbread?cookies=8").dispatch();
+        assert_eq!(response.status(), Status::Conflict);
+        for bad in ["-1", "abc", ""] {
+            let uri = format!("/bake/shortbread?cookies={bad}");
+            assert_eq!(client.post(uri).dispatch().status(), Status::BadRequest);
+        }
+        let pantry: HashMap<String, usize> = client.get("/pantry").dispatch().into_json().unwrap();
+        assert_eq!(pantry["flour"], 700);
+
+        let response = client.post("/bake/shortbread").dispatch();
+        let baked: BakeResponse = response.into_json().unwrap();
+        assert_eq!(baked.cookies, 7);
+
+        let pantry: HashMap<String, usize> = client.get("/pantry").dispatch().into_json().unwrap();
+        assert_eq!(
+            pantry,
+            HashMap::from([("flour".to_string(), 0), ("butter".to_string(), 0)])
+        );
+    }
+
+    #[test]
+    fn test_unreadable_bakery_is_not_overwritten() {
+        let state = bakery_state();
+        state.persist.save(BAKERY_KEY, 7u8).unwrap();
+        let persist = state.persist.clone();
+        let config = RecipeConfig {
+            recipe_secret: "north pole".to_string(),
+            strict_recipes: false,
+        };
+        let rocket = rocket::build()
+            .mount("/", routes())
+            .manage(config)
+            .manage(state);
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+
+        assert_eq!(
+            client.get("/pantry").dispatch().status(),
+            Status::InternalServerError
+        );
+        let response = client.put("/pantry").body(r#"{"flour":1}"#).dispatch();
+        assert_eq!(response.status(), Status::InternalServerError);
+        assert_eq!(persist.load::<u8>(BAKERY_KEY).unwrap(), 7);
+    }
+
+    #[test]
+    fn test_day12_save_does_not_touch_bakery() {
+        let state = bakery_state();
+        let state12 = crate::day12::Day12State {
+            persist: state.persist.clone(),
+        };
+        let config = RecipeConfig {
+            recipe_secret: "north pole".to_string(),
+            strict_recipes: false,
+        };
+        let rocket = rocket::build()
+            .mount("/", routes())
+            .mount("/12", crate::day12::routes())
+            .manage(config)
+            .manage(state)
+            .manage(state12);
+        let client = Client::tracked(rocket).expect("valid rocket instance");
+
+        stock_bakery(&client);
+        for packet_id in [BAKERY_KEY, "bakery"] {
+            let response = client.post(format!("/12/save/{packet_id}")).dispatch();
+            assert_eq!(response.status(), Status::Ok);
+        }
+
+        let pantry: HashMap<String, usize> = client.get("/pantry").dispatch().into_json().unwrap();
+        assert_eq!(pantry["flour"], 1000);
+    }
+
+    #[test]
+    fn test_reservations() {
+        let client = recipe_client(false);
+        stock_bakery(&client);
+
+        let response = client
+            .post("/reservations")
+            .body(r#"{"recipe":"shortbread","cookies":6}"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::Created);
+        let held: ReservationResponse = response.into_json().unwrap();
+        assert_eq!(held.reservation.ingredients["flour"], 600);
+
+        // Only 4 batches are left unreserved, and the pantry can't shrink below what's held
+        let response = client.post("/bake/shortbread?cookies=5").dispatch();
+        assert_eq!(response.status(), Status::Conflict);
+        let response = client
+            .put("/pantry")
+            .body(r#"{"flour":500,"butter":500}"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::Conflict);
+        let response = client.post("/bake/shortbread").dispatch();
+        assert_eq!(response.into_json::<BakeResponse>().unwrap().cookies, 4);
+
+        let commit = format!("/reservations/{}/commit", held.id);
+        let baked: BakeResponse = client.post(commit.as_str()).dispatch().into_json().unwrap();
+        assert_eq!(baked.cookies, 6);
+        assert_eq!(baked.pantry["flour"], 0);
+        assert_eq!(
+            client.post(commit.as_str()).dispatch().status(),
+            Status::NotFound
+        );
+
+        // Released holds go back to the pantry
+        client
+            .put("/pantry")
+            .body(r#"{"flour":100,"butter":50}"#)
+            .dispatch();
+        let response = client
+            .post("/reservations")
+            .body(r#"{"recipe":"shortbread","cookies":1,"seconds":60}"#)
+            .dispatch();
+        let held: ReservationResponse = response.into_json().unwrap();
+        let path = format!("/reservations/{}", held.id);
+        assert_eq!(client.get(path.as_str()).dispatch().status(), Status::Ok);
+        assert_eq!(client.delete(path.as_str()).dispatch().status(), Status::Ok);
+        assert_eq!(
+            client.get(path.as_str()).dispatch().status(),
+            Status::NotFound
+        );
+        let response = client.post("/bake/shortbread").dispatch();
+        assert_eq!(response.into_json::<BakeResponse>().unwrap().cookies, 1);
+
+        let response = client
+            .post("/reservations")
+            .body(r#"{"recipe":"shortbread","cookies":1,"seconds":86400}"#)
+            .dispatch();
+        assert_eq!(response.status(), Status::BadRequest);
+    }
+
+    #[rocket::async_test]
+    async fn test_concurrent_bakes_do_not_oversell() {
+        use rocket::futures::future::join_all;
+        use rocket::local::asynchronous::Client;
+
+        let config = RecipeConfig {
+            recipe_secret: "north pole".to_string(),
+            strict_recipes: false,
+        };
+        let rocket = rocket::build()
+            .mount("/", routes())
+            .manage(config)
+            .manage(bakery_state());
+        let client = Client::untracked(rocket).await.unwrap();
+        client
+            .put("/pantry")
+            .body(r#"{"flour":1000,"butter":500}"#)
+            .dispatch()
+            .await;
+        client
+            .put("/recipes/shortbread")
+            .body(r#"{"flour":100,"butter":50}"#)
+            .dispatch()
+            .await;
+
+        let bakes = (0..25).map(|_| async {
+            let response = client.post("/bake/shortbread?cookies=1").dispatch().await;
+            response.status()
+        });
+        let statuses = join_all(bakes).await;
+        assert_eq!(statuses.iter().filter(|&&s| s == Status::Ok).count(), 10);
+        assert_eq!(
+            statuses.iter().filter(|&&s| s == Status::Conflict).count(),
+            15
+        );
+
+        let response = client.get("/pantry").dispatch().await;
+        let pantry: HashMap<String, usize> = response.into_json().await.unwrap();
+        assert_eq!(pantry["flour"], 0);
+    }
 }
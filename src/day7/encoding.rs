@@ -0,0 +1,162 @@
+use base64::{engine::general_purpose, Engine as _};
+use flate2::read::{GzDecoder, ZlibDecoder};
+use rocket::http::Status;
+use std::io::Read;
+
+// Far more than any recipe needs, and small enough that a zip bomb can't hurt
+pub const MAX_RECIPE_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug)]
+pub enum DecodeError {
+    Base64(String),
+    Decompress(String),
+    TooLarge(u64),
+    Utf8(std::string::FromUtf8Error),
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::Base64(e) => write!(f, "base64 decoding failed: {e}"),
+            DecodeError::Decompress(e) => write!(f, "decompression failed: {e}"),
+            DecodeError::TooLarge(limit) => {
+                write!(f, "decompression failed: recipe is over {limit} bytes")
+            }
+            DecodeError::Utf8(e) => write!(f, "UTF-8 decoding failed: {e}"),
+        }
+    }
+}
+
+impl DecodeError {
+    pub fn status(&self) -> Status {
+        match self {
+            DecodeError::TooLarge(_) => Status::PayloadTooLarge,
+            _ => Status::BadRequest,
+        }
+    }
+}
+
+// Accepts standard or URL-safe base64, padded or not, of plain, gzip or zlib wrapped recipes
+pub fn decode(value: &str) -> Result<String, DecodeError> {
+    let bytes = decode_base64(value)?;
+    let bytes = decompress(&bytes, MAX_RECIPE_BYTES)?;
+    String::from_utf8(bytes).map_err(DecodeError::Utf8)
+}
+
+fn decode_base64(value: &str) -> Result<Vec<u8>, DecodeError> {
+    let unpadded = value.trim_end_matches('=');
+    let url_safe = unpadded.contains(['-', '_']);
+    if url_safe && unpadded.contains(['+', '/']) {
+        return Err(DecodeError::Base64(
+            "mixes the standard and URL-safe alphabets".to_string(),
+        ));
+    }
+
+    let engine = if url_safe {
+        &general_purpose::URL_SAFE_NO_PAD
+    } else {
+        &general_purpose::STANDARD_NO_PAD
+    };
+    engine
+        .decode(unpadded)
+        .map_err(|e| DecodeError::Base64(e.to_string()))
+}
+
+// Picks the format from the magic bytes; JSON starts with neither, so plain recipes pass through.
+// "deflate" is the zlib wrapped kind that HTTP means by it, since raw deflate has no header to spot
+fn decompress(bytes: &[u8], limit: u64) -> Result<Vec<u8>, DecodeError> {
+    let reader: Box<dyn Read + '_> = match bytes {
+        [0x1f, 0x8b, ..] => Box::new(GzDecoder::new(bytes)),
+        [cmf, flg, ..] if cmf & 0x0f == 8 && (u16::from(*cmf) << 8 | u16::from(*flg)) % 31 == 0 => {
+            Box::new(ZlibDecoder::new(bytes))
+        }
+        _ => Box::new(bytes),
+    };
+
+    // Reading one byte past the limit is enough to tell it was exceeded
+    let mut decompressed = Vec::new();
+    reader
+        .take(limit + 1)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| DecodeError::Decompress(e.to_string()))?;
+    if decompressed.len() as u64 > limit {
+        return Err(DecodeError::TooLarge(limit));
+    }
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::{GzEncoder, ZlibEncoder};
+    use flate2::Compression;
+    use std::io::Write;
+
+    const RECIPE: &str = r#"{"recipe":{"flour":95},"pantry":{"flour":385}}"#;
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn zlib(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_base64_variants() {
+        // Chosen so the standard and URL-safe encodings differ
+        let recipe = r#"{"recipe":{"flour???":95}}"#;
+        for engine in [
+            general_purpose::STANDARD,
+            general_purpose::STANDARD_NO_PAD,
+            general_purpose::URL_SAFE,
+            general_purpose::URL_SAFE_NO_PAD,
+        ] {
+            let encoded = engine.encode(recipe);
+            assert_eq!(decode(&encoded).unwrap(), recipe, "{encoded}");
+        }
+
+        let mixed = format!("{}-", general_purpose::STANDARD.encode("???>>>"));
+        assert!(matches!(decode(&mixed), Err(DecodeError::Base64(_))));
+        assert!(matches!(decode("not base64"), Err(DecodeError::Base64(_))));
+    }
+
+    #[test]
+    fn test_compressed() {
+        for compressed in [gzip(RECIPE.as_bytes()), zlib(RECIPE.as_bytes())] {
+            let encoded = general_purpose::URL_SAFE_NO_PAD.encode(compressed);
+            assert_eq!(decode(&encoded).unwrap(), RECIPE);
+        }
+
+        let mut truncated = gzip(RECIPE.as_bytes());
+        truncated.truncate(20);
+        let encoded = general_purpose::STANDARD.encode(truncated);
+        let error = decode(&encoded).unwrap_err();
+        assert!(matches!(error, DecodeError::Decompress(_)));
+        assert_eq!(error.status(), Status::BadRequest);
+    }
+
+    #[test]
+    fn test_zip_bomb() {
+        let bomb = gzip(&vec![b' '; 10 * 1024 * 1024]);
+        assert!(bomb.len() < 16 * 1024);
+        let encoded = general_purpose::STANDARD.encode(bomb);
+        let error = decode(&encoded).unwrap_err();
+        assert!(matches!(error, DecodeError::TooLarge(MAX_RECIPE_BYTES)));
+        assert_eq!(error.status(), Status::PayloadTooLarge);
+
+        let exact = vec![b' '; MAX_RECIPE_BYTES as usize];
+        assert_eq!(decompress(&gzip(&exact), MAX_RECIPE_BYTES).unwrap(), exact);
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let encoded = general_purpose::STANDARD.encode(gzip(&[0, 159, 146, 150]));
+        let error = decode(&encoded).unwrap_err();
+        assert!(error.to_string().starts_with("UTF-8 decoding failed"));
+    }
+}
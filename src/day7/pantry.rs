@@ -0,0 +1,272 @@
+use rocket::serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Everything the bakery keeps between requests, saved as one value so updates are all or nothing
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Bakery {
+    pub stock: HashMap<String, usize>,
+    pub recipes: HashMap<String, HashMap<String, usize>>,
+    pub reservations: HashMap<String, Reservation>,
+}
+
+// Ingredients held for a bake until `expires_at`, in Unix seconds
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Reservation {
+    pub recipe: String,
+    pub cookies: usize,
+    pub ingredients: HashMap<String, usize>,
+    pub expires_at: i64,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BakeryError {
+    UnknownRecipe(String),
+    UnknownReservation(String),
+    Insufficient {
+        ingredient: String,
+        needed: usize,
+        available: usize,
+    },
+    TooMany,
+}
+
+impl std::fmt::Display for BakeryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BakeryError::UnknownRecipe(name) => write!(f, "No recipe called {name}"),
+            BakeryError::UnknownReservation(id) => write!(f, "No reservation {id}"),
+            BakeryError::Insufficient {
+                ingredient,
+                needed,
+                available,
+            } => write!(f, "Need {needed} {ingredient} but only {available} is free"),
+            BakeryError::TooMany => write!(f, "Too many cookies to count the ingredients"),
+        }
+    }
+}
+
+impl Bakery {
+    // Drops reservations past their time, returning whether any were
+    pub fn expire(&mut self, now: i64) -> bool {
+        let before = self.reservations.len();
+        self.reservations.retain(|_, r| r.expires_at > now);
+        self.reservations.len() != before
+    }
+
+    // Stock not held by a reservation
+    pub fn available(&self) -> HashMap<String, usize> {
+        let mut available = self.stock.clone();
+        for reservation in self.reservations.values() {
+            for (ingredient, amount) in &reservation.ingredients {
+                if let Some(left) = available.get_mut(ingredient) {
+                    *left = left.saturating_sub(*amount);
+                }
+            }
+        }
+        available
+    }
+
+    fn recipe(&self, name: &str) -> Result<&HashMap<String, usize>, BakeryError> {
+        self.recipes
+            .get(name)
+            .ok_or_else(|| BakeryError::UnknownRecipe(name.to_string()))
+    }
+
+    // What `cookies` of the recipe take, leaving out ingredients it needs none of
+    fn ingredients(
+        &self,
+        recipe: &str,
+        cookies: usize,
+    ) -> Result<HashMap<String, usize>, BakeryError> {
+        self.recipe(recipe)?
+            .iter()
+            .filter(|(_, &amount)| amount > 0)
+            .map(|(ingredient, amount)| {
+                let needed = amount.checked_mul(cookies).ok_or(BakeryError::TooMany)?;
+                Ok((ingredient.clone(), needed))
+            })
+            .collect()
+    }
+
+    fn check(
+        needed: &HashMap<String, usize>,
+        available: &HashMap<String, usize>,
+    ) -> Result<(), BakeryError> {
+        for (ingredient, &needed) in needed {
+            let available = available.get(ingredient).copied().unwrap_or(0);
+            if needed > available {
+                return Err(BakeryError::Insufficient {
+                    ingredient: ingredient.clone(),
+                    needed,
+                    available,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn deduct(&mut self, used: &HashMap<String, usize>) {
+        for (ingredient, amount) in used {
+            if let Some(left) = self.stock.get_mut(ingredient) {
+                *left -= amount;
+            }
+        }
+    }
+
+    // Replaces the stock, as long as every reservation is still covered
+    pub fn restock(&mut self, stock: HashMap<String, usize>) -> Result<(), BakeryError> {
+        let mut reserved: HashMap<String, usize> = HashMap::new();
+        for reservation in self.reservations.values() {
+            for (ingredient, amount) in &reservation.ingredients {
+                *reserved.entry(ingredient.clone()).or_default() += amount;
+            }
+        }
+        Self::check(&reserved, &stock)?;
+
+        self.stock = stock;
+        Ok(())
+    }
+
+    // Bakes from unreserved stock, `cookies` of them or as many as it allows
+    pub fn bake(&mut self, recipe: &str, cookies: Option<usize>) -> Result<usize, BakeryError> {
+        let cookies = match cookies {
+            Some(cookies) => cookies,
+            None => {
+                super::calc_baked_cookies(self.recipe(recipe)?.clone(), self.available()).cookies
+            }
+        };
+        let used = self.ingredients(recipe, cookies)?;
+        Self::check(&used, &self.available())?;
+
+        self.deduct(&used);
+        Ok(cookies)
+    }
+
+    pub fn reserve(
+        &mut self,
+        id: String,
+        recipe: &str,
+        cookies: usize,
+        expires_at: i64,
+    ) -> Result<&Reservation, BakeryError> {
+        let ingredients = self.ingredients(recipe, cookies)?;
+        Self::check(&ingredients, &self.available())?;
+
+        let reservation = Reservation {
+            recipe: recipe.to_string(),
+            cookies,
+            ingredients,
+            expires_at,
+        };
+        Ok(self.reservations.entry(id).or_insert(reservation))
+    }
+
+    // Takes the held ingredients out of stock for good
+    pub fn commit(&mut self, id: &str) -> Result<Reservation, BakeryError> {
+        let reservation = self.release(id)?;
+        self.deduct(&reservation.ingredients);
+        Ok(reservation)
+    }
+
+    pub fn release(&mut self, id: &str) -> Result<Reservation, BakeryError> {
+        self.reservations
+            .remove(id)
+            .ok_or_else(|| BakeryError::UnknownReservation(id.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bakery() -> Bakery {
+        Bakery {
+            stock: HashMap::from([("flour".to_string(), 500), ("sugar".to_string(), 200)]),
+            recipes: HashMap::from([(
+                "shortbread".to_string(),
+                HashMap::from([
+                    ("flour".to_string(), 100),
+                    ("sugar".to_string(), 20),
+                    ("salt".to_string(), 0),
+                ]),
+            )]),
+            reservations: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_bake() {
+        let mut bakery = bakery();
+        assert_eq!(bakery.bake("shortbread", Some(2)), Ok(2));
+        assert_eq!(bakery.stock["flour"], 300);
+        assert_eq!(
+            bakery.bake("shortbread", Some(4)),
+            Err(BakeryError::Insufficient {
+                ingredient: "flour".to_string(),
+                needed: 400,
+                available: 300
+            })
+        );
+        assert_eq!(bakery.bake("shortbread", None), Ok(3));
+        assert_eq!(bakery.stock["flour"], 0);
+        assert_eq!(bakery.stock["sugar"], 100);
+        assert_eq!(bakery.bake("shortbread", None), Ok(0));
+        assert_eq!(
+            bakery.bake("gingerbread", None),
+            Err(BakeryError::UnknownRecipe("gingerbread".to_string()))
+        );
+        assert_eq!(
+            bakery.bake("shortbread", Some(usize::MAX)),
+            Err(BakeryError::TooMany)
+        );
+    }
+
+    #[test]
+    fn test_reservations_hold_stock() {
+        let mut bakery = bakery();
+        bakery
+            .reserve("a".to_string(), "shortbread", 3, 100)
+            .unwrap();
+
+        assert_eq!(bakery.available()["flour"], 200);
+        assert!(bakery
+            .reserve("b".to_string(), "shortbread", 3, 100)
+            .is_err());
+        assert_eq!(bakery.bake("shortbread", None), Ok(2));
+        assert_eq!(bakery.stock["flour"], 300);
+
+        // Can't restock below what's held
+        let low = HashMap::from([("flour".to_string(), 200), ("sugar".to_string(), 100)]);
+        assert!(bakery.restock(low).is_err());
+
+        let committed = bakery.commit("a").unwrap();
+        assert_eq!(committed.cookies, 3);
+        assert_eq!(bakery.stock["flour"], 0);
+        assert_eq!(
+            bakery.commit("a"),
+            Err(BakeryError::UnknownReservation("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_reservations_expire() {
+        let mut bakery = bakery();
+        bakery
+            .reserve("a".to_string(), "shortbread", 5, 100)
+            .unwrap();
+        assert_eq!(bakery.available()["flour"], 0);
+
+        assert!(!bakery.expire(99));
+        assert!(bakery.expire(100));
+        assert_eq!(bakery.available()["flour"], 500);
+
+        bakery
+            .reserve("b".to_string(), "shortbread", 5, 200)
+            .unwrap();
+        bakery.release("b").unwrap();
+        assert_eq!(bakery.stock["flour"], 500);
+        assert_eq!(bakery.available()["flour"], 500);
+    }
+}
@@ -0,0 +1,360 @@
+// Integer bake planning: maximize Σ value[r]·count[r] subject to Σ needs[r][i]·count[r] <= supply[i].
+// Branch and bound over the LP relaxation, with every candidate plan checked in exact integers.
+
+// Branch and bound gives up on proving optimality after this many LP solves
+const MAX_NODES: usize = 20_000;
+// Each LP solve is a dense simplex over about (ingredients + recipes) × (2 × recipes + ingredients)
+// cells, so these keep a full search to around a second
+pub const MAX_RECIPES: usize = 16;
+pub const MAX_INGREDIENTS: usize = 16;
+// Counts can reach u64::MAX, so this keeps value × count, and the LP objective, finite
+pub const MAX_VALUE: f64 = 1e12;
+const EPSILON: f64 = 1e-9;
+
+pub struct Problem {
+    pub values: Vec<f64>,
+    // Per recipe, the amount of each ingredient one cookie takes
+    pub needs: Vec<Vec<u64>>,
+    pub supply: Vec<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Solution {
+    pub counts: Vec<u64>,
+    pub value: f64,
+    // False if the node limit was hit before the best plan was proven best
+    pub optimal: bool,
+}
+
+// Maximizes c·x subject to a·x <= b and x >= 0, for b >= 0 so the origin is feasible.
+// Bland's rule keeps it from cycling; None means the objective is unbounded.
+fn simplex(a: &[Vec<f64>], b: &[f64], c: &[f64]) -> Option<Vec<f64>> {
+    let (rows, cols) = (a.len(), c.len());
+    let width = cols + rows + 1;
+
+    // Slack variables start as the basis; the last row holds the negated objective
+    let mut tableau = vec![vec![0.0; width]; rows + 1];
+    for (i, (row, (a, &b))) in tableau.iter_mut().zip(a.iter().zip(b)).enumerate() {
+        row[..cols].copy_from_slice(a);
+        row[cols + i] = 1.0;
+        row[width - 1] = b;
+    }
+    for (objective, &c) in tableau[rows].iter_mut().zip(c) {
+        *objective = -c;
+    }
+    let mut basis: Vec<usize> = (cols..cols + rows).collect();
+
+    while let Some(entering) = (0..width - 1).find(|&j| tableau[rows][j] < -EPSILON) {
+        let mut leaving: Option<usize> = None;
+        for (i, row) in tableau[..rows].iter().enumerate() {
+            if row[entering] <= EPSILON {
+                continue;
+            }
+            let ratio = row[width - 1] / row[entering];
+            leaving = match leaving {
+                Some(l) => {
+                    let best = tableau[l][width - 1] / tableau[l][entering];
+                    if ratio < best - EPSILON || (ratio <= best + EPSILON && basis[i] < basis[l]) {
+                        Some(i)
+                    } else {
+                        Some(l)
+                    }
+                }
+                None => Some(i),
+            };
+        }
+        let leaving = leaving?;
+
+        let pivot = tableau[leaving][entering];
+        tableau[leaving].iter_mut().for_each(|v| *v /= pivot);
+        let pivot_row = tableau[leaving].clone();
+        for (i, row) in tableau.iter_mut().enumerate() {
+            let factor = row[entering];
+            if i != leaving && factor != 0.0 {
+                for (v, p) in row.iter_mut().zip(&pivot_row) {
+                    *v -= factor * p;
+                }
+            }
+        }
+        basis[leaving] = entering;
+    }
+
+    let mut x = vec![0.0; cols];
+    for (i, &var) in basis.iter().enumerate() {
+        if var < cols {
+            x[var] = tableau[i][width - 1].max(0.0);
+        }
+    }
+    Some(x)
+}
+
+impl Problem {
+    fn value(&self, counts: &[u64]) -> f64 {
+        counts
+            .iter()
+            .zip(&self.values)
+            .map(|(&n, &v)| n as f64 * v)
+            .sum()
+    }
+
+    // Supply left after taking out `counts`, or None if it doesn't stretch that far
+    fn remaining(&self, counts: &[u64]) -> Option<Vec<u64>> {
+        let mut remaining = self.supply.clone();
+        for (needs, &count) in self.needs.iter().zip(counts) {
+            for (left, &need) in remaining.iter_mut().zip(needs) {
+                let used = (need as u128) * (count as u128);
+                *left = u64::try_from((*left as u128).checked_sub(used)?).ok()?;
+            }
+        }
+        Some(remaining)
+    }
+
+    // How many more of `recipe` the remaining supply allows, capped at `cap`
+    fn room(&self, recipe: usize, remaining: &[u64], cap: u64) -> u64 {
+        self.needs[recipe]
+            .iter()
+            .zip(remaining)
+            .filter(|(&need, _)| need > 0)
+            .map(|(&need, &left)| left / need)
+            .fold(cap, u64::min)
+    }
+
+    // Rounds an LP point down to a feasible plan within bounds, then tops it up greedily
+    fn round(&self, lower: &[u64], upper: &[Option<u64>], x: &[f64]) -> Option<Vec<u64>> {
+        let mut remaining = self.remaining(lower)?;
+        let mut counts = lower.to_vec();
+
+        let mut order: Vec<usize> = (0..self.values.len()).collect();
+        for pass in 0..2 {
+            for &r in &order {
+                let cap = upper[r].map_or(u64::MAX, |u| u - counts[r]);
+                let wanted = match pass {
+                    // `x` counts the lower bound too, which is already in `counts`
+                    0 => ((x[r] + EPSILON).floor() as u64)
+                        .saturating_sub(lower[r])
+                        .min(cap),
+                    _ if self.values[r] > 0.0 => cap,
+                    _ => 0,
+                };
+                let extra = self.room(r, &remaining, wanted);
+                counts[r] += extra;
+                for (left, &need) in remaining.iter_mut().zip(&self.needs[r]) {
+                    *left -= need * extra;
+                }
+            }
+            // Top up the most valuable recipes first
+            order.sort_by(|&a, &b| self.values[b].total_cmp(&self.values[a]));
+        }
+
+        Some(counts)
+    }
+
+    // The LP optimum with every count held between its bounds, as absolute counts
+    fn relax(&self, lower: &[u64], upper: &[Option<u64>]) -> Option<Vec<f64>> {
+        let remaining = self.remaining(lower)?;
+
+        // Count each recipe in units of the most it could reach on its own, then scale each row,
+        // so a pantry of 10^18 works with the same tolerances as one of 10
+        let columns: Vec<f64> = (0..self.values.len())
+            .map(|r| {
+                let cap = upper[r].map_or(u64::MAX, |u| u - lower[r]);
+                self.room(r, &remaining, cap).max(1) as f64
+            })
+            .collect();
+        let mut a = vec![];
+        let mut b = vec![];
+        for (i, &left) in remaining.iter().enumerate() {
+            let row: Vec<f64> = self
+                .needs
+                .iter()
+                .zip(&columns)
+                .map(|(needs, column)| needs[i] as f64 * column)
+                .collect();
+            let scale = row.iter().copied().fold(left as f64, f64::max).max(1.0);
+            a.push(row.iter().map(|v| v / scale).collect());
+            b.push(left as f64 / scale);
+        }
+        for (r, bound) in upper.iter().enumerate() {
+            if let Some(u) = bound {
+                let mut row = vec![0.0; self.values.len()];
+                row[r] = 1.0;
+                a.push(row);
+                b.push((u - lower[r]) as f64 / columns[r]);
+            }
+        }
+        let c: Vec<f64> = self
+            .values
+            .iter()
+            .zip(&columns)
+            .map(|(v, s)| v * s)
+            .collect();
+        let c_scale = c
+            .iter()
+            .map(|v| v.abs())
+            .fold(0.0, f64::max)
+            .max(f64::MIN_POSITIVE);
+        let c: Vec<f64> = c.iter().map(|v| v / c_scale).collect();
+
+        let y = simplex(&a, &b, &c)?;
+        Some(
+            y.iter()
+                .zip(&columns)
+                .zip(lower)
+                .map(|((y, column), &l)| y * column + l as f64)
+                .collect(),
+        )
+    }
+
+    pub fn solve(&self) -> Solution {
+        let n = self.values.len();
+        let mut best = vec![0; n];
+        let mut best_value = 0.0;
+        let mut nodes = 0;
+
+        let mut stack = vec![(vec![0u64; n], vec![None; n])];
+        while let Some((lower, upper)) = stack.pop() {
+            if nodes == MAX_NODES {
+                return Solution {
+                    value: best_value,
+                    counts: best,
+                    optimal: false,
+                };
+            }
+            nodes += 1;
+
+            let x = match self.relax(&lower, &upper) {
+                Some(x) => x,
+                None => continue,
+            };
+            let bound: f64 = x.iter().zip(&self.values).map(|(x, v)| x * v).sum();
+            if bound <= best_value + EPSILON * best_value.abs().max(1.0) {
+                continue;
+            }
+
+            if let Some(counts) = self.round(&lower, &upper, &x) {
+                let value = self.value(&counts);
+                if value > best_value {
+                    best_value = value;
+                    best = counts;
+                }
+            }
+
+            // Branch on the most fractional count
+            let fraction = |x: f64| (x - x.floor()).min(x.ceil() - x);
+            let split = (0..n)
+                .filter(|&r| fraction(x[r]) > 1e-6 * x[r].max(1.0))
+                .max_by(|&a, &b| fraction(x[a]).total_cmp(&fraction(x[b])));
+            if let Some(r) = split {
+                let floor = x[r].floor() as u64;
+                let mut down = upper.clone();
+                down[r] = Some(floor.max(lower[r]));
+                stack.push((lower.clone(), down));
+                let mut up = lower;
+                up[r] = floor + 1;
+                let within = match upper[r] {
+                    Some(u) => up[r] <= u,
+                    None => true,
+                };
+                if within {
+                    stack.push((up, upper));
+                }
+            }
+        }
+
+        Solution {
+            value: best_value,
+            counts: best,
+            optimal: true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    // Tries every combination, for checking the solver on tiny problems
+    fn brute_force(problem: &Problem) -> f64 {
+        fn search(problem: &Problem, counts: &mut Vec<u64>, r: usize) -> f64 {
+            if r == problem.values.len() {
+                return problem.value(counts);
+            }
+            let mut best = f64::MIN;
+            loop {
+                if problem.remaining(counts).is_none() {
+                    break;
+                }
+                best = best.max(search(problem, counts, r + 1));
+                counts[r] += 1;
+            }
+            counts[r] = 0;
+            best
+        }
+        search(problem, &mut vec![0; problem.values.len()], 0)
+    }
+
+    #[test]
+    fn test_greedy_is_not_enough() {
+        // The best value per flour is A, but two Bs fill the pantry exactly
+        let problem = Problem {
+            values: vec![7.0, 5.0],
+            needs: vec![vec![6], vec![5]],
+            supply: vec![10],
+        };
+        let solution = problem.solve();
+        assert_eq!(solution.counts, vec![0, 2]);
+        assert_eq!(solution.value, 10.0);
+        assert!(solution.optimal);
+    }
+
+    // Up to three recipes over up to three ingredients, small enough to brute force.
+    // Every recipe needs something, since one that doesn't is unbounded.
+    fn problems() -> impl Strategy<Value = (Vec<f64>, Vec<Vec<u64>>, Vec<u64>)> {
+        (1..=3usize, 1..=3usize).prop_flat_map(|(recipes, ingredients)| {
+            let needs = prop::collection::vec(0..6u64, ingredients)
+                .prop_filter("needs nothing", |needs| needs.iter().any(|&n| n > 0));
+            (
+                prop::collection::vec((1..=9u32).prop_map(f64::from), recipes),
+                prop::collection::vec(needs, recipes),
+                prop::collection::vec(0..30u64, ingredients),
+            )
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn test_matches_brute_force((values, needs, supply) in problems()) {
+            let problem = Problem { values, needs, supply };
+            let solution = problem.solve();
+            prop_assert!(problem.remaining(&solution.counts).is_some());
+            prop_assert_eq!(solution.value, brute_force(&problem));
+        }
+    }
+
+    #[test]
+    fn test_rounds_above_lower_bounds() {
+        // The LP optimum has 1.5 of the first, and the branch with at least 2 of it is best
+        let problem = Problem {
+            values: vec![6.0, 1.0, 4.0],
+            needs: vec![vec![2, 0], vec![1, 0], vec![1, 5]],
+            supply: vec![6, 15],
+        };
+        let solution = problem.solve();
+        assert_eq!(solution.counts, vec![2, 0, 2]);
+        assert_eq!(solution.value, 20.0);
+    }
+
+    #[test]
+    fn test_huge_supply() {
+        let problem = Problem {
+            values: vec![1.0, 3.0],
+            needs: vec![vec![1, 1], vec![3, 2]],
+            supply: vec![1_000_000_000_000_000_000, 1_000_000_000_000_000_001],
+        };
+        let solution = problem.solve();
+        // Flour runs out either way, and both recipes are worth the same per unit of flour
+        assert!(problem.remaining(&solution.counts).is_some());
+        assert_eq!(solution.value, 1e18);
+    }
+}
@@ -0,0 +1,182 @@
+use rocket::serde::{Deserialize, Serialize};
+
+// Beyond this many units of odd-sized packs, working out the cheapest mix is refused
+pub const MAX_PACK_TABLE: u64 = 1_000_000;
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Pack {
+    pub size: u64,
+    pub price: f64,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PackCount {
+    #[serde(flatten)]
+    pub pack: Pack,
+    pub count: u64,
+}
+
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct Purchase {
+    pub packs: Vec<PackCount>,
+    // What the packs add up to, which can be more than was needed
+    pub quantity: u64,
+    pub cost: f64,
+}
+
+// The cheapest set of packs adding up to at least `needed`.
+// Some optimum buys fewer than `size` of anything but the best value pack: any `size` other packs
+// contain a subset whose sizes sum to a multiple of `size`, which best value packs can replace for
+// no more. So only that much needs the table, and the rest is best value packs.
+pub fn cheapest(packs: &[Pack], needed: u64) -> Result<Option<Purchase>, String> {
+    let packs: Vec<&Pack> = packs.iter().filter(|p| p.size > 0).collect();
+    if let Some(p) = packs.iter().find(|p| !p.price.is_finite() || p.price < 0.0) {
+        return Err(format!(
+            "Invalid price {} for a pack of {}",
+            p.price, p.size
+        ));
+    }
+    let best = match packs
+        .iter()
+        .min_by(|a, b| (a.price / a.size as f64).total_cmp(&(b.price / b.size as f64)))
+    {
+        Some(best) => *best,
+        None => return Ok(None),
+    };
+
+    // Cheapest way to buy exactly `x` from the other packs, and the last pack used to get there.
+    // Overshooting by a whole pack is never cheaper, which keeps small orders small.
+    let largest = packs.iter().map(|p| p.size).max().unwrap();
+    let limit = (best.size - 1)
+        .saturating_mul(largest)
+        .min(needed.saturating_add(largest));
+    if limit > MAX_PACK_TABLE {
+        return Err(format!(
+            "Pack sizes up to {largest} are too varied to compare for {needed}"
+        ));
+    }
+    let limit = limit as usize;
+    let mut cost = vec![f64::INFINITY; limit + 1];
+    let mut last = vec![usize::MAX; limit + 1];
+    cost[0] = 0.0;
+    for x in 1..=limit {
+        for (i, pack) in packs.iter().enumerate() {
+            let size = pack.size as usize;
+            if size <= x && cost[x - size] + pack.price < cost[x] {
+                cost[x] = cost[x - size] + pack.price;
+                last[x] = i;
+            }
+        }
+    }
+
+    let (x, bulk, total) = (0..=limit)
+        .filter(|&x| cost[x].is_finite())
+        .map(|x| {
+            let bulk = needed.saturating_sub(x as u64).div_ceil(best.size);
+            (x, bulk, cost[x] + bulk as f64 * best.price)
+        })
+        .min_by(|a, b| a.2.total_cmp(&b.2))
+        .unwrap();
+
+    let mut counts = vec![0u64; packs.len()];
+    let best_index = packs.iter().position(|p| std::ptr::eq(*p, best)).unwrap();
+    counts[best_index] += bulk;
+    let mut rest = x;
+    while rest > 0 {
+        counts[last[rest]] += 1;
+        rest -= packs[last[rest]].size as usize;
+    }
+
+    let packs: Vec<PackCount> = packs
+        .iter()
+        .zip(counts)
+        .filter(|(_, count)| *count > 0)
+        .map(|(pack, count)| PackCount {
+            pack: (*pack).clone(),
+            count,
+        })
+        .collect();
+    Ok(Some(Purchase {
+        quantity: packs.iter().map(|p| p.pack.size * p.count).sum(),
+        packs,
+        cost: total,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pack(size: u64, price: f64) -> Pack {
+        Pack { size, price }
+    }
+
+    fn counts(purchase: &Purchase) -> Vec<(u64, u64)> {
+        purchase
+            .packs
+            .iter()
+            .map(|p| (p.pack.size, p.count))
+            .collect()
+    }
+
+    #[test]
+    fn test_single_pack() {
+        let purchase = cheapest(&[pack(500, 2.0)], 1200).unwrap().unwrap();
+        assert_eq!(counts(&purchase), vec![(500, 3)]);
+        assert_eq!(purchase.quantity, 1500);
+        assert_eq!(purchase.cost, 6.0);
+    }
+
+    #[test]
+    fn test_mixed_packs() {
+        // 1000g is the best value, but 1000 + 250 beats 2 x 1000 for 1200g
+        let packs = [pack(1000, 4.0), pack(250, 1.5)];
+        let purchase = cheapest(&packs, 1200).unwrap().unwrap();
+        assert_eq!(counts(&purchase), vec![(1000, 1), (250, 1)]);
+        assert_eq!(purchase.cost, 5.5);
+
+        let purchase = cheapest(&packs, 1_000_000_000_000).unwrap().unwrap();
+        assert_eq!(counts(&purchase), vec![(1000, 1_000_000_000)]);
+
+        // A small pack beats a bulk one for a pinch
+        let purchase = cheapest(&packs, 1).unwrap().unwrap();
+        assert_eq!(counts(&purchase), vec![(250, 1)]);
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let packs = [pack(7, 5.0), pack(3, 2.5), pack(2, 1.8)];
+        for needed in 0..60 {
+            let mut best = f64::INFINITY;
+            for a in 0..=9 {
+                for b in 0..=20 {
+                    for c in 0..=30 {
+                        if 7 * a + 3 * b + 2 * c >= needed {
+                            best = best.min(a as f64 * 5.0 + b as f64 * 2.5 + c as f64 * 1.8);
+                        }
+                    }
+                }
+            }
+            let purchase = cheapest(&packs, needed).unwrap().unwrap();
+            assert!((purchase.cost - best).abs() < 1e-9, "{needed}");
+            assert!(purchase.quantity >= needed);
+        }
+    }
+
+    #[test]
+    fn test_invalid_packs() {
+        assert_eq!(cheapest(&[], 10), Ok(None));
+        assert_eq!(cheapest(&[pack(0, 1.0)], 10), Ok(None));
+        assert!(cheapest(&[pack(5, -1.0)], 10).is_err());
+        assert!(cheapest(&[pack(1_000_000, 1.0), pack(1_000_001, 2.0)], 10).is_err());
+
+        // Gram sized packs only get too varied for big orders
+        let grams = [pack(1000, 1.0), pack(1500, 1.2)];
+        let small = cheapest(&grams, 10).unwrap().unwrap();
+        assert_eq!((small.quantity, small.cost), (1000, 1.0));
+        assert!(cheapest(&grams, 10_000_000).is_err());
+    }
+}
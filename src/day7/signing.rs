@@ -0,0 +1,81 @@
+use base64::{engine::general_purpose, Engine as _};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Base64 never contains a '.', so it can't be mistaken for part of a legacy recipe
+const SEPARATOR: char = '.';
+
+#[derive(Debug, PartialEq)]
+pub enum Verified<'a> {
+    Signed(&'a str),
+    // A legacy cookie with no signature at all
+    Unsigned(&'a str),
+}
+
+fn mac(payload: &str, secret: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret).expect("HMAC takes keys of any length");
+    mac.update(payload.as_bytes());
+    mac
+}
+
+// `<payload>.<signature>`, the signature being URL-safe base64 without padding
+pub fn sign(payload: &str, secret: &[u8]) -> String {
+    let signature = mac(payload, secret).finalize().into_bytes();
+    format!(
+        "{payload}{SEPARATOR}{}",
+        general_purpose::URL_SAFE_NO_PAD.encode(signature)
+    )
+}
+
+// The payload of a cookie value, or an error if it carries a signature that doesn't match
+pub fn verify<'a>(value: &'a str, secret: &[u8]) -> Result<Verified<'a>, String> {
+    let (payload, signature) = match value.split_once(SEPARATOR) {
+        Some(parts) => parts,
+        None => return Ok(Verified::Unsigned(value)),
+    };
+
+    let signature = general_purpose::URL_SAFE_NO_PAD
+        .decode(signature)
+        .map_err(|e| format!("Malformed signature on {value}: {e}"))?;
+    mac(payload, secret)
+        .verify_slice(&signature)
+        .map_err(|_| format!("Signature mismatch on {value}"))?;
+
+    Ok(Verified::Signed(payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &[u8] = b"north pole";
+
+    #[test]
+    fn test_round_trip() {
+        let signed = sign("eyJmbG91ciI6MTAwfQ==", SECRET);
+        assert_eq!(
+            verify(&signed, SECRET),
+            Ok(Verified::Signed("eyJmbG91ciI6MTAwfQ=="))
+        );
+    }
+
+    #[test]
+    fn test_unsigned() {
+        assert_eq!(
+            verify("eyJmbG91ciI6MTAwfQ==", SECRET),
+            Ok(Verified::Unsigned("eyJmbG91ciI6MTAwfQ=="))
+        );
+    }
+
+    #[test]
+    fn test_tampered() {
+        let signed = sign("eyJmbG91ciI6MTAwfQ==", SECRET);
+        let (_, signature) = signed.split_once('.').unwrap();
+
+        assert!(verify(&format!("eyJmbG91ciI6OTk5fQ==.{signature}"), SECRET).is_err());
+        assert!(verify(&signed, b"south pole").is_err());
+        assert!(verify("eyJmbG91ciI6MTAwfQ==.not*base64", SECRET).is_err());
+    }
+}
@@ -0,0 +1,326 @@
+use rocket::serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Leftovers are rounded to this many decimal places to hide float noise
+const LEFTOVER_DECIMALS: i32 = 6;
+
+// A bare amount, or one with a unit like `{"amount": 1.5, "unit": "kg"}`
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(crate = "rocket::serde", untagged)]
+pub enum Quantity {
+    Bare(usize),
+    Measured { amount: f64, unit: String },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dimension {
+    Mass,
+    Volume,
+    Count,
+}
+
+// How many grams, millilitres or pieces one of the unit is
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Unit {
+    pub dimension: Dimension,
+    pub factor: f64,
+}
+
+const UNITS: &[(&[&str], Dimension, f64)] = &[
+    (&["mg", "milligram", "milligrams"], Dimension::Mass, 0.001),
+    (&["g", "gram", "grams"], Dimension::Mass, 1.0),
+    (&["kg", "kilogram", "kilograms"], Dimension::Mass, 1000.0),
+    (&["oz", "ounce", "ounces"], Dimension::Mass, 28.349523125),
+    (
+        &["lb", "lbs", "pound", "pounds"],
+        Dimension::Mass,
+        453.59237,
+    ),
+    (
+        &[
+            "ml",
+            "millilitre",
+            "millilitres",
+            "milliliter",
+            "milliliters",
+        ],
+        Dimension::Volume,
+        1.0,
+    ),
+    (
+        &["l", "litre", "litres", "liter", "liters"],
+        Dimension::Volume,
+        1000.0,
+    ),
+    (
+        &["tsp", "teaspoon", "teaspoons"],
+        Dimension::Volume,
+        4.92892159375,
+    ),
+    (
+        &["tbsp", "tablespoon", "tablespoons"],
+        Dimension::Volume,
+        14.78676478125,
+    ),
+    (&["cup", "cups"], Dimension::Volume, 236.5882365),
+    (
+        &["piece", "pieces", "pc", "pcs", "each"],
+        Dimension::Count,
+        1.0,
+    ),
+    (&["dozen"], Dimension::Count, 12.0),
+];
+
+// Grams per millilitre, for going between cups and grams; requests can add to or override these
+const DENSITIES: &[(&str, f64)] = &[
+    ("flour", 0.593),
+    ("sugar", 0.845),
+    ("brown sugar", 0.721),
+    ("butter", 0.911),
+    ("milk", 1.035),
+    ("water", 1.0),
+    ("honey", 1.42),
+    ("cocoa", 0.52),
+    ("chocolate chips", 0.72),
+];
+
+pub fn parse_unit(name: &str) -> Option<Unit> {
+    let name = name.trim().to_lowercase();
+    UNITS
+        .iter()
+        .find(|(names, _, _)| names.contains(&name.as_str()))
+        .map(|&(_, dimension, factor)| Unit { dimension, factor })
+}
+
+fn density(ingredient: &str, densities: &HashMap<String, f64>) -> Option<f64> {
+    densities.get(ingredient).copied().or_else(|| {
+        DENSITIES
+            .iter()
+            .find(|(name, _)| *name == ingredient)
+            .map(|&(_, density)| density)
+    })
+}
+
+// Converts a base amount (grams, millilitres or pieces) of `ingredient` to another dimension's base
+fn convert(
+    amount: f64,
+    from: Dimension,
+    to: Dimension,
+    ingredient: &str,
+    densities: &HashMap<String, f64>,
+) -> Result<f64, String> {
+    let density = || {
+        density(ingredient, densities).ok_or_else(|| {
+            format!("No density for {ingredient} to convert between mass and volume")
+        })
+    };
+    match (from, to) {
+        (from, to) if from == to => Ok(amount),
+        (Dimension::Volume, Dimension::Mass) => Ok(amount * density()?),
+        (Dimension::Mass, Dimension::Volume) => Ok(amount / density()?),
+        _ => Err(format!(
+            "Can't convert {ingredient} from {from:?} to {to:?}"
+        )),
+    }
+}
+
+fn measured(ingredient: &str, amount: f64, unit: &str) -> Result<Unit, String> {
+    if !amount.is_finite() || amount < 0.0 {
+        return Err(format!("Invalid amount {amount} of {ingredient}"));
+    }
+    parse_unit(unit).ok_or_else(|| format!("Unknown unit {unit} for {ingredient}"))
+}
+
+fn round_leftover(amount: f64) -> f64 {
+    let scale = 10f64.powi(LEFTOVER_DECIMALS);
+    ((amount * scale).round() / scale).max(0.0)
+}
+
+// Like `calc_baked_cookies`, converting each pantry amount to the recipe's units first.
+// Bare amounts are only comparable with other bare amounts, and stay exact.
+pub fn bake(
+    recipe: &HashMap<String, Quantity>,
+    pantry: &HashMap<String, Quantity>,
+    densities: &HashMap<String, f64>,
+) -> Result<(usize, HashMap<String, Quantity>), String> {
+    if let Some((ingredient, _)) = densities.iter().find(|(_, d)| !d.is_finite() || **d <= 0.0) {
+        return Err(format!("Invalid density for {ingredient}"));
+    }
+
+    // Per needed ingredient, how many cookies it alone would allow
+    let mut limits: HashMap<&String, usize> = HashMap::new();
+    for (ingredient, needed) in recipe {
+        let limit = match (needed, pantry.get(ingredient)) {
+            (Quantity::Bare(0), _) => continue,
+            (Quantity::Bare(_), None) => 0,
+            (Quantity::Bare(needed), Some(Quantity::Bare(have))) => have / needed,
+            (
+                Quantity::Measured {
+                    amount: needed,
+                    unit,
+                },
+                have,
+            ) => {
+                let unit = measured(ingredient, *needed, unit)?;
+                if *needed == 0.0 {
+                    continue;
+                }
+                let have = match have {
+                    None => 0.0,
+                    Some(Quantity::Measured {
+                        amount,
+                        unit: pantry_unit,
+                    }) => {
+                        let pantry_unit = measured(ingredient, *amount, pantry_unit)?;
+                        let have = amount * pantry_unit.factor;
+                        convert(
+                            have,
+                            pantry_unit.dimension,
+                            unit.dimension,
+                            ingredient,
+                            densities,
+                        )?
+                    }
+                    Some(Quantity::Bare(_)) => return Err(mixed(ingredient)),
+                };
+                // Allow for float noise like 0.3 / 0.1 = 2.9999999999999996
+                (have / (needed * unit.factor) + 1e-9).floor() as usize
+            }
+            (Quantity::Bare(_), Some(Quantity::Measured { .. })) => return Err(mixed(ingredient)),
+        };
+        limits.insert(ingredient, limit);
+    }
+    let cookies = limits.values().copied().min().unwrap_or(0);
+
+    let mut leftover = pantry.clone();
+    for (ingredient, left) in leftover.iter_mut() {
+        if !limits.contains_key(ingredient) {
+            continue;
+        }
+        *left = match (&recipe[ingredient], &*left) {
+            // cookies * needed <= amount, as for the plain bake
+            (Quantity::Bare(needed), Quantity::Bare(amount)) => {
+                Quantity::Bare(amount - needed * cookies)
+            }
+            (
+                Quantity::Measured {
+                    amount: needed,
+                    unit: recipe_unit,
+                },
+                Quantity::Measured { amount, unit },
+            ) => {
+                let recipe_unit = parse_unit(recipe_unit).unwrap();
+                let pantry_unit = parse_unit(unit).unwrap();
+                let used = needed * recipe_unit.factor * cookies as f64;
+                let used = convert(
+                    used,
+                    recipe_unit.dimension,
+                    pantry_unit.dimension,
+                    ingredient,
+                    densities,
+                )?;
+                Quantity::Measured {
+                    amount: round_leftover(amount - used / pantry_unit.factor),
+                    unit: unit.clone(),
+                }
+            }
+            _ => unreachable!("mixed quantities are rejected above"),
+        };
+    }
+
+    Ok((cookies, leftover))
+}
+
+fn mixed(ingredient: &str) -> String {
+    format!("{ingredient} needs a unit in both recipe and pantry, or in neither")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grams(amount: f64, unit: &str) -> Quantity {
+        Quantity::Measured {
+            amount,
+            unit: unit.to_string(),
+        }
+    }
+
+    fn quantities(items: &[(&str, Quantity)]) -> HashMap<String, Quantity> {
+        items
+            .iter()
+            .map(|(name, q)| (name.to_string(), q.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_unit() {
+        assert_eq!(
+            parse_unit(" Cups"),
+            Some(Unit {
+                dimension: Dimension::Volume,
+                factor: 236.5882365
+            })
+        );
+        assert_eq!(parse_unit("kg").unwrap().factor, 1000.0);
+        assert_eq!(parse_unit("smidgen"), None);
+    }
+
+    #[test]
+    fn test_bake_mixed_units() {
+        let recipe = quantities(&[
+            ("flour", grams(250.0, "g")),
+            ("milk", grams(0.5, "cup")),
+            ("eggs", Quantity::Bare(2)),
+            ("salt", grams(0.0, "tsp")),
+        ]);
+        let pantry = quantities(&[
+            ("flour", grams(1.1, "kg")),
+            ("milk", grams(1.0, "l")),
+            ("eggs", Quantity::Bare(9)),
+            ("sugar", grams(3.0, "cups")),
+        ]);
+
+        let (cookies, leftover) = bake(&recipe, &pantry, &HashMap::new()).unwrap();
+        assert_eq!(cookies, 4);
+        assert_eq!(leftover["flour"], grams(0.1, "kg"));
+        assert_eq!(leftover["milk"], grams(0.526824, "l"));
+        assert_eq!(leftover["eggs"], Quantity::Bare(1));
+        assert_eq!(leftover["sugar"], grams(3.0, "cups"));
+    }
+
+    #[test]
+    fn test_bake_with_density() {
+        let recipe = quantities(&[("flour", grams(1.0, "cup"))]);
+        let pantry = quantities(&[("flour", grams(1.0, "kg"))]);
+
+        // 1 cup of flour is about 140g with the built in density
+        let (cookies, leftover) = bake(&recipe, &pantry, &HashMap::new()).unwrap();
+        assert_eq!(cookies, 7);
+        assert_eq!(leftover["flour"], grams(0.017922, "kg"));
+
+        let densities = HashMap::from([("flour".to_string(), 0.5)]);
+        let (cookies, _) = bake(&recipe, &pantry, &densities).unwrap();
+        assert_eq!(cookies, 8);
+    }
+
+    #[test]
+    fn test_bake_errors() {
+        let pantry = quantities(&[("eggs", grams(2.0, "dozen")), ("oil", grams(1.0, "l"))]);
+
+        let recipe = quantities(&[("eggs", grams(100.0, "g"))]);
+        assert!(bake(&recipe, &pantry, &HashMap::new()).is_err());
+        let recipe = quantities(&[("oil", grams(100.0, "g"))]);
+        assert!(bake(&recipe, &pantry, &HashMap::new()).is_err());
+        let recipe = quantities(&[("oil", Quantity::Bare(1))]);
+        assert!(bake(&recipe, &pantry, &HashMap::new()).is_err());
+        let recipe = quantities(&[("oil", grams(1.0, "smidgen"))]);
+        assert!(bake(&recipe, &pantry, &HashMap::new()).is_err());
+        let recipe = quantities(&[("oil", grams(-1.0, "ml"))]);
+        assert!(bake(&recipe, &pantry, &HashMap::new()).is_err());
+
+        let recipe = quantities(&[("oil", grams(100.0, "g"))]);
+        let densities = HashMap::from([("oil".to_string(), 0.92)]);
+        assert_eq!(bake(&recipe, &pantry, &densities).unwrap().0, 9);
+    }
+}
@@ -1,7 +1,10 @@
 use crate::day12::Day12State;
 use crate::day13::Day13State;
+use crate::day4::Day4State;
+use crate::day7::Day7State;
 use shuttle_persist::PersistInstance;
 //use sqlx::PgPool;
+use rocket::fairing::AdHoc;
 use rocket_dyn_templates::Template;
 
 mod day0;
@@ -20,10 +23,14 @@ mod day8;
 async fn main(
     #[shuttle_persist::Persist] persist: PersistInstance,
     #[shuttle_persist::Persist] persist2: PersistInstance,
+    #[shuttle_persist::Persist] persist3: PersistInstance,
+    #[shuttle_persist::Persist] persist4: PersistInstance,
     /* DB provisioning is fucked on my M3 #[shuttle_shared_db::Postgres] pool: PgPool, */
 ) -> shuttle_rocket::ShuttleRocket {
     let state12 = Day12State { persist };
     let state13 = Day13State { persist: persist2 };
+    let state4 = Day4State::new(persist3);
+    let state7 = Day7State::new(persist4);
     let rocket = rocket::build()
         .mount("/", day0::routes())
         .mount("/1", day1::routes())
@@ -36,9 +43,12 @@ async fn main(
         .mount("/13", day13::routes())
         .mount("/14", day14::routes())
         .mount("/15", day15::routes())
+        .manage(state4)
+        .manage(state7)
         .manage(state12)
         .manage(state13)
-        .attach(Template::fairing());
+        .attach(Template::fairing())
+        .attach(AdHoc::config::<day7::RecipeConfig>());
 
     Ok(rocket.into())
 }